@@ -1,18 +1,37 @@
 pub mod directory;
 pub mod list;
 pub mod project;
+pub mod restart;
+pub mod stop;
+pub mod sync;
 pub mod template;
+pub mod wait;
 
 use clap::{Parser, Subcommand};
+use moxide::{formats::Format, schema::SchemaKind};
+use std::ffi::OsStr;
+
+/// Set to anything but an empty string to silence warnings about broken config files
+pub const QUIET_VAR: &str = "MOXIDE_QUIET";
 
 /// A CLI for tmux session management
 #[derive(Parser, Debug)]
 #[clap(version)]
 pub struct Cli {
+    /// Don't warn about config files that can't be parsed
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
     #[command(subcommand)]
     pub cmd: Commands,
 }
 
+impl Cli {
+    /// Whether warnings are silenced by `--quiet` or by `quiet_var`, the value of `QUIET_VAR`
+    pub fn is_quiet(&self, quiet_var: Option<&OsStr>) -> bool {
+        self.quiet || quiet_var.is_some_and(|value| !value.is_empty())
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Initialize the config
@@ -46,6 +65,26 @@ pub enum Commands {
     /// List all moxide directories, templates and projecets
     #[command(alias = "ls")]
     List(list::ListCli),
+    /// Stop sessions started by moxide
+    ///
+    /// Stops the given session, or all sessions of a kind with `--kind`, or every session with `--all`.
+    #[command(alias = "kill")]
+    Stop(stop::StopArgs),
+    /// Rebuild a session from its config
+    ///
+    /// The focused window and pane and the attached clients are kept.
+    Restart(restart::RestartArgs),
+    /// Check all config files for mistakes
+    ///
+    /// Exits with a non-zero code if any problem is found.
+    Check,
+    /// Print the JSON Schema of a config file
+    ///
+    /// Editors can use it for completion and validation of the configs.
+    Schema {
+        #[arg(value_enum)]
+        kind: SchemaKind,
+    },
     /// Save the current session into a new template
     #[command(alias = "fre", alias = "save")]
     Freeze {
@@ -61,5 +100,37 @@ pub enum Commands {
         /// Use stdout instead of a file
         #[arg(long, default_value_t = false, conflicts_with = "file")]
         stdout: bool,
+        /// The format of the config, defaults to the extension of the file name or YAML
+        #[arg(long, value_enum)]
+        format: Option<Format>,
     },
+    /// Block until a pane is ready, used for `wait_for` of panes
+    #[command(hide = true)]
+    Wait(wait::WaitArgs),
+    /// Type a command into a pane once its shell is idle
+    #[command(hide = true)]
+    Type(wait::TypeArgs),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_flag() {
+        let cli = Cli::try_parse_from(["moxide", "list", "--quiet"]).unwrap();
+        assert!(cli.quiet);
+        let cli = Cli::try_parse_from(["moxide", "-q", "project", "list"]).unwrap();
+        assert!(cli.quiet);
+        let cli = Cli::try_parse_from(["moxide", "list"]).unwrap();
+        assert!(!cli.quiet);
+    }
+
+    #[test]
+    fn test_quiet_var() {
+        let cli = Cli::try_parse_from(["moxide", "list"]).unwrap();
+        assert!(!cli.is_quiet(None));
+        assert!(!cli.is_quiet(Some(OsStr::new(""))));
+        assert!(cli.is_quiet(Some(OsStr::new("1"))));
+    }
 }
@@ -1,4 +1,6 @@
+use super::sync::SyncArgs;
 use clap::{Parser, Subcommand};
+use moxide::variables::parse_key_value;
 
 #[derive(Subcommand, Debug)]
 pub enum ProjectCommands {
@@ -11,6 +13,11 @@ pub enum ProjectCommands {
     },
     /// Start a specific project
     Start(ProjectStartArgs),
+    /// Update the running session of a project to match its config
+    ///
+    /// Missing windows and panes are created, windows are renamed and their layouts applied again.
+    /// Existing panes are never killed.
+    Sync(SyncArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -25,4 +32,12 @@ pub struct ProjectStartArgs {
     /// Always start a new session instead of attaching to an existing session
     #[arg(short = 'n', long, default_value_t = false)]
     pub always_new_session: bool,
+
+    /// Set a template variable, can be passed multiple times
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub vars: Vec<(String, String)>,
+
+    /// Block until all panes with `wait_for` are ready before attaching
+    #[arg(short, long, default_value_t = false)]
+    pub wait: bool,
 }
@@ -0,0 +1,12 @@
+use clap::Parser;
+use moxide::variables::parse_key_value;
+
+#[derive(Parser, Debug)]
+pub struct RestartArgs {
+    /// The session to restart
+    pub name: String,
+
+    /// Set a template variable, can be passed multiple times
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub vars: Vec<(String, String)>,
+}
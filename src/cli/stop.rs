@@ -0,0 +1,25 @@
+use clap::Parser;
+use moxide::session::SessionKind;
+
+#[derive(Parser, Debug)]
+pub struct StopArgs {
+    /// The session to stop, only sessions started by moxide can be stopped
+    #[arg(required_unless_present_any = ["all", "kind"], conflicts_with = "all")]
+    pub name: Option<String>,
+
+    /// Stop all sessions started by moxide
+    #[arg(short, long, default_value_t = false)]
+    pub all: bool,
+
+    /// Only stop sessions started from a project, a template or a directory
+    #[arg(short, long, value_enum)]
+    pub kind: Option<SessionKind>,
+
+    /// Send C-c to every pane and give them time to finish before the session is killed
+    #[arg(short, long, default_value_t = false)]
+    pub graceful: bool,
+
+    /// Seconds a graceful stop waits for the panes
+    #[arg(short, long, default_value_t = 10, requires = "graceful")]
+    pub timeout: u64,
+}
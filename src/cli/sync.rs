@@ -0,0 +1,16 @@
+use clap::Parser;
+use moxide::variables::parse_key_value;
+
+#[derive(Parser, Debug)]
+pub struct SyncArgs {
+    /// The name of the config whose running sessions are synced
+    pub name: String,
+
+    /// Kill windows that aren't in the config anymore
+    #[arg(short, long, default_value_t = false)]
+    pub prune: bool,
+
+    /// Set a template variable, can be passed multiple times
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub vars: Vec<(String, String)>,
+}
@@ -1,4 +1,6 @@
+use super::sync::SyncArgs;
 use clap::{Parser, Subcommand};
+use moxide::variables::parse_key_value;
 
 #[derive(Subcommand, Debug)]
 pub enum TemplateCommands {
@@ -12,6 +14,11 @@ pub enum TemplateCommands {
         all: bool,
     },
     Start(StartTemplateArgs),
+    /// Update the running sessions of a template to match its config
+    ///
+    /// Missing windows and panes are created, windows are renamed and their layouts applied again.
+    /// Existing panes are never killed.
+    Sync(SyncArgs),
 }
 
 #[derive(Parser, Debug)]
@@ -35,4 +42,12 @@ pub struct StartTemplateArgs {
     /// Always start a new session instead of attaching to an existing session
     #[arg(long, default_value_t = false)]
     pub always_new_session: bool,
+
+    /// Set a template variable, can be passed multiple times
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub vars: Vec<(String, String)>,
+
+    /// Block until all panes with `wait_for` are ready before attaching
+    #[arg(short, long, default_value_t = false)]
+    pub wait: bool,
 }
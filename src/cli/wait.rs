@@ -0,0 +1,39 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+pub struct WaitArgs {
+    /// Seconds until the wait is given up
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+
+    /// A TCP port on localhost that has to accept connections
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// A file that has to exist
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+
+    /// The position of the pane in the current window whose output is checked
+    #[arg(long, requires = "output")]
+    pub pane: Option<usize>,
+
+    /// A regex the output of the pane has to match
+    #[arg(long, requires = "pane")]
+    pub output: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct TypeArgs {
+    /// The pane the command is typed into
+    #[arg(long)]
+    pub pane: String,
+
+    /// Seconds to wait for an idle shell, the command is typed anyway afterwards
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+
+    /// The command line that is typed
+    pub command_line: String,
+}
@@ -0,0 +1,202 @@
+use crate::exit::ExitErr;
+use moxide::{
+    config_files::{config_files, name_matches, ConfigFile},
+    directories::{directories_file_in, parse_directories, ParseDirectoryError},
+    helpers::{absolute_path, get_config_dir},
+    projects::{Project, ProjectSetup},
+    templates::{Template, TemplateError, Window},
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Collects the problems of the config, every problem starts with the file it is in
+#[derive(Debug, Default)]
+struct Checker {
+    problems: Vec<String>,
+}
+
+impl Checker {
+    fn report<M: std::fmt::Display>(&mut self, path: &Path, message: M) {
+        self.problems.push(format!("{}: {message}", path.display()));
+    }
+
+    /// Parses all files in the directory, files that can't be parsed are reported
+    fn parse_all<T: serde::de::DeserializeOwned>(&mut self, dir: &Path) -> Vec<(ConfigFile, T)> {
+        let files = match config_files(dir) {
+            Ok(files) => files,
+            Err(err) => {
+                self.report(dir, format!("Can't read the directory: {err}"));
+                return Vec::new();
+            }
+        };
+
+        files
+            .into_iter()
+            .filter_map(|file| match file.try_parse::<T>() {
+                Ok(config) => Some((file, config)),
+                Err(err) => {
+                    self.problems.push(err.to_string());
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Reports a name that differs from the file name and names that are used twice
+    fn check_name(&mut self, file: &ConfigFile, name: &str, names: &mut BTreeMap<String, PathBuf>) {
+        if file.stem().is_some_and(|stem| stem != name) {
+            self.report(
+                &file.path,
+                format!("The name {name} doesn't match the file name"),
+            );
+        }
+
+        let qualified = file.qualify(name);
+        if let Some(other) = names.get(&qualified) {
+            let message = format!("The name {qualified} is also used by {}", other.display());
+            self.report(&file.path, message);
+        } else {
+            names.insert(qualified, file.path.clone());
+        }
+    }
+
+    fn check_windows(&mut self, path: &Path, windows: &[Window]) {
+        for window in windows {
+            if let Err(err) = window.check() {
+                self.report(path, err);
+            }
+        }
+    }
+
+    fn check_templates(&mut self, config_dir: &Path) -> Vec<String> {
+        let templates = self.parse_all::<Template>(&config_dir.join("templates/"));
+
+        let mut names = BTreeMap::new();
+        for (file, template) in &templates {
+            self.check_name(file, &template.name, &mut names);
+            self.check_windows(&file.path, &template.windows);
+        }
+
+        let lookup = |query: &str| {
+            let base = templates
+                .iter()
+                .find(|(file, template)| name_matches(&file.qualify(&template.name), query));
+            Ok::<_, TemplateError>(base.map(|(_, template)| template.clone()))
+        };
+        for (file, template) in &templates {
+            if let Err(err) = template.clone().resolve_extends(&lookup) {
+                self.report(&file.path, err);
+            }
+        }
+
+        names.into_keys().collect()
+    }
+
+    fn check_projects(&mut self, config_dir: &Path, template_names: &[String]) {
+        let projects = self.parse_all::<Project>(&config_dir.join("projects/"));
+
+        let mut names = BTreeMap::new();
+        for (file, project) in projects {
+            self.check_name(&file, &project.name, &mut names);
+
+            if absolute_path(&project.root_dir).is_err() {
+                let message = format!("The root_dir {} doesn't exist", project.root_dir.display());
+                self.report(&file.path, message);
+            }
+
+            match &project.setup {
+                ProjectSetup::Template {
+                    templates,
+                    overrides,
+                } => {
+                    for template in templates {
+                        if !template_names
+                            .iter()
+                            .any(|name| name_matches(name, template))
+                        {
+                            let message = format!("The template {template} doesn't exist");
+                            self.report(&file.path, message);
+                        }
+                    }
+                    self.check_windows(&file.path, &overrides.override_windows);
+                    self.check_windows(&file.path, &overrides.extra_windows);
+                }
+                ProjectSetup::Windows { windows } => self.check_windows(&file.path, windows),
+            }
+        }
+    }
+
+    fn check_directories(&mut self, path: PathBuf) {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) => return self.report(&path, format!("Can't read the file: {err}")),
+        };
+
+        match parse_directories(&content, &path) {
+            Ok(_) => {}
+            Err(err @ ParseDirectoryError::Format { .. }) => self.problems.push(err.to_string()),
+            Err(err) => self.report(&path, err),
+        }
+    }
+}
+
+/// All problems of the config in `config_dir`
+fn check_config(config_dir: &Path) -> Vec<String> {
+    let mut checker = Checker::default();
+
+    checker.check_directories(directories_file_in(config_dir));
+    let template_names = checker.check_templates(config_dir);
+    checker.check_projects(config_dir, &template_names);
+
+    checker.problems
+}
+
+pub fn check_handler() {
+    let config_dir = get_config_dir().exit_err(1);
+    let problems = check_config(&config_dir);
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    match problems.len() {
+        0 => println!("No problems found"),
+        count => {
+            eprintln!("Found {count} problem(s)");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_config() {
+        let config_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/check");
+        let problems: Vec<_> = check_config(&config_dir)
+            .iter()
+            .map(|problem| problem.replace(&format!("{}/", config_dir.display()), ""))
+            .collect();
+
+        assert_eq!(
+            problems,
+            [
+                "templates/Broken.yaml:5:1: did not find expected ',' or ']' at line 5 column 1, \
+                 while parsing a flow sequence at line 4 column 12",
+                "templates/Panes.yaml: The split tree of window Split has 2 panes, but 3 panes are defined",
+                "templates/Cycle.yaml: Templates extend each other in a cycle: Cycle -> Loop -> Cycle",
+                "templates/Loop.yaml: Templates extend each other in a cycle: Loop -> Cycle -> Loop",
+                "templates/Orphan.yaml: Template Missing could not be found",
+                "projects/Web.yaml: The name Api doesn't match the file name",
+                "projects/Web.yaml: The name Api is also used by projects/Api.yaml",
+                "projects/Web.yaml: The root_dir /nonexistent/moxide-check-fixture doesn't exist",
+                "projects/Web.yaml: The template Svelte doesn't exist",
+            ]
+        );
+    }
+}
@@ -1,12 +1,18 @@
 use crate::{
     cli::directory::{DirectoryCommands, StartDirectoryArgs},
+    exit::{Exit, ExitErr},
+};
+use moxide::{
     directories::{parse_directory_config, Directories},
-    helpers::{absolute_path, dir_name, Exit, ExitErr},
-    tmux::{attach, session_exists},
+    helpers::{absolute_path, dir_name},
+    session::{
+        attach_session, directory_template, find_session, start_session, SessionKind, SessionTag,
+        StartOptions,
+    },
+    tmux::get_unused_name,
     widgets::table::Table,
 };
 use std::path::PathBuf;
-use tmux_interface::{NewSession, Tmux};
 
 pub fn directory_handler(action: DirectoryCommands) {
     match action {
@@ -38,43 +44,45 @@ fn format_dirs_minimal(dirs: Directories) -> String {
 
 fn start_handler(args: &StartDirectoryArgs) {
     let (name, path) = resolve_dir_path(args);
-    let exists = session_exists(&name).unwrap_or(false);
+    let tag = SessionTag {
+        kind: SessionKind::Dir,
+        name: name.clone(),
+        root: Some(path),
+    };
 
-    let mut tmux = Tmux::new();
-    if args.always_new_session || !exists {
-        let cmd = NewSession::new()
-            .start_directory(path.to_string_lossy())
-            .detached()
-            .session_name(&name)
-            .window_name(&name);
-        tmux = tmux.add_command(cmd);
-    }
-    if !args.detached {
-        tmux = tmux.add_command(attach(&name));
+    if !args.always_new_session {
+        if let Some(session) = find_session(&tag).exit_err(1) {
+            attach_session(&session, args.detached).exit(1, "Could not switch to the Tmux session");
+            return;
+        }
     }
 
-    tmux.output()
-        .exit(1, "Could not switch to the Tmux session");
+    let name = get_unused_name(&args.name.clone().unwrap_or(name));
+    let template = directory_template(&name);
+    let options = StartOptions {
+        detached: args.detached,
+        ..StartOptions::default()
+    };
+    start_session(&name, &tag, &template, &template.windows, options).exit_err(1);
 }
 
+/// The name of the directory in the config, or of the directory itself, and its path
 fn resolve_dir_path(cli_args: &StartDirectoryArgs) -> (String, PathBuf) {
     let name = &cli_args.directory;
 
     let dirs = parse_directory_config().exit_err(1);
     let dir = dirs.get(name);
-    let user_name = cli_args.name.clone();
 
     match dir {
         Some(dir) => (
-            user_name.unwrap_or_else(|| name.clone()),
+            name.clone(),
             absolute_path(dir).exit(1, "The path could not be generated"),
         ),
         None => {
             let relative_path = PathBuf::from(&cli_args.directory);
             let path = absolute_path(&relative_path).exit(1, "The path could not be generated");
-            let name = user_name.unwrap_or_else(|| dir_name(&path));
 
-            (name, path)
+            (dir_name(&path), path)
         }
     }
 }
@@ -1,4 +1,4 @@
-use crate::init;
+use moxide::init;
 
 pub fn init_handler() {
     match init::init_config() {
@@ -1,39 +1,54 @@
-use crate::{
-    cli::list::ListCli,
+use crate::{cli::list::ListCli, exit::ExitErr};
+use moxide::{
     directories,
-    helpers::{format_name, ExitErr},
-    projects, templates,
-    tmux::session_exists,
+    helpers::format_name,
+    index::IndexEntry,
+    projects,
+    session::{managed_sessions, SessionKind},
+    templates,
 };
 
 pub fn list_handler(args: ListCli) {
-    let mut projects = projects::parse_project_config();
-    let mut templates = templates::parse_template_config();
+    let mut projects = projects::indexed_projects().exit_err(1);
+    let mut templates = templates::indexed_templates().exit_err(1);
     let dirs = directories::parse_directory_config().exit_err(1);
+    let running = if args.running {
+        managed_sessions().exit_err(1)
+    } else {
+        Vec::new()
+    };
+    let is_running = |kind: SessionKind, name: &str| {
+        !args.running
+            || running
+                .iter()
+                .any(|(_, tag)| tag.kind == kind && tag.name == name)
+    };
+
     let dirs = dirs
         .names()
-        .filter(|name| !args.running || session_exists(*name).unwrap_or(false));
-
-    if args.running {
-        projects.retain(|project| session_exists(&project.name).unwrap_or(false));
-        templates.retain(|template| session_exists(&template.name).unwrap_or(false));
-    }
+        .filter(|name| is_running(SessionKind::Dir, name));
+    let entry_is_running = |kind, entry: &IndexEntry| {
+        entry
+            .name
+            .as_deref()
+            .is_some_and(|name| is_running(kind, name))
+    };
+    projects.retain(|entry| entry_is_running(SessionKind::Project, entry));
+    templates.retain(|entry| entry_is_running(SessionKind::Template, entry));
 
-    for project in projects {
-        println!(
-            "{}",
-            format_name(args.format_project.as_deref(), &project.name)
-        );
+    for name in projects
+        .iter()
+        .filter_map(|project| project.name.as_deref())
+    {
+        println!("{}", format_name(args.format_project.as_deref(), name));
     }
 
-    for template in templates {
-        let is_hidden = template.hidden.unwrap_or(false);
-        if args.all || !is_hidden {
-            println!(
-                "{}",
-                format_name(args.format_template.as_deref(), &template.name)
-            );
-        }
+    let templates = templates
+        .iter()
+        .filter(|template| args.all || !template.hidden)
+        .filter_map(|template| template.name.as_deref());
+    for name in templates {
+        println!("{}", format_name(args.format_template.as_deref(), name));
     }
 
     for name in dirs {
@@ -1,6 +1,11 @@
+pub mod check;
 pub mod directory;
 pub mod freeze;
 pub mod init;
 pub mod list;
 pub mod project;
+pub mod restart;
+pub mod schema;
+pub mod stop;
 pub mod template;
+pub mod wait;
@@ -1,64 +1,71 @@
 use crate::{
-    cli::project::{ProjectCommands, ProjectStartArgs},
-    helpers::{self, apply_if_some, Exit},
-    projects::find_project,
-    projects::parse_project_config,
-    templates::apply_windows,
+    cli::{
+        project::{ProjectCommands, ProjectStartArgs},
+        sync::SyncArgs,
+    },
+    exit::{Exit, ExitErr},
+};
+use moxide::{
+    helpers,
+    projects::{find_project, parse_project_config},
+    session::{attach_session, find_session, start_session, SessionKind, SessionTag, StartOptions},
+    sync::sync_sessions,
     tmux,
     widgets::{heading::Heading, table::Table},
 };
-use tmux_interface::{NewSession, Tmux};
 
 pub fn project_handler(action: ProjectCommands) {
     match action {
         ProjectCommands::List { minimal } => list_handler(minimal),
         ProjectCommands::Start(args) => start_handler(args),
+        ProjectCommands::Sync(args) => sync_handler(args),
     }
 }
 
 fn list_handler(minimal: bool) {
-    for proj in parse_project_config() {
+    for proj in parse_project_config().exit_err(1) {
         if minimal {
             println!("{}", proj.name);
         } else {
             println!("{}", Heading(proj.name));
-            println!("{}", Table::from(proj.setup));
+            println!("{}", Table::try_from(proj.setup).exit_err(1));
         }
     }
 }
 
 fn start_handler(args: ProjectStartArgs) {
-    let project = find_project(&args.name).exit(1, "Project could not be found");
-
-    let detached = args.detached;
+    let project = find_project(&args.name).exit_err(1);
+    let path = helpers::absolute_path(&project.root_dir).exit(1, "The path could not be found");
+    let tag = SessionTag {
+        kind: SessionKind::Project,
+        name: project.name.clone(),
+        root: Some(path),
+    };
 
-    if tmux::session_exists(&project.name).unwrap_or(false) && !args.always_new_session {
-        apply_if_some(
-            Tmux::new(),
-            (!detached).then(|| tmux::attach(&project.name)),
-            |tmux, cmd| tmux.add_command(cmd),
-        )
-        .output()
-        .exit(1, "Could not attach to the Tmux-session");
-        return;
+    if !args.always_new_session {
+        if let Some(session) = find_session(&tag).exit_err(1) {
+            attach_session(&session, args.detached).exit(1, "Could not attach to the Tmux-session");
+            return;
+        }
     }
 
     let name = tmux::get_unused_name(&project.name);
-    let windows = Vec::from(project.setup);
-    let path = helpers::absolute_path(&project.root_dir).exit(1, "The path could not be found");
-
-    let new_session_cmd = NewSession::new()
-        .detached()
-        .session_name(&name)
-        .start_directory(path.to_string_lossy().into_owned());
+    let template = project.into_template().exit_err(1);
+    let windows = template
+        .resolve_windows(&args.vars.into_iter().collect())
+        .exit_err(1);
 
-    let initial_tmux = apply_if_some(
-        Tmux::new().add_command(new_session_cmd),
-        (!detached).then(|| tmux::attach(&name)),
-        |tmux, cmd| tmux.add_command(cmd),
-    );
+    let options = StartOptions {
+        detached: args.detached,
+        wait: args.wait,
+    };
+    start_session(&name, &tag, &template, &windows, options).exit_err(1);
+}
 
-    apply_windows(initial_tmux, &windows, Some(&path))
-        .output()
-        .exit(1, "Could not start Tmux-session");
+fn sync_handler(args: SyncArgs) {
+    let vars = args.vars.into_iter().collect();
+    let sessions = sync_sessions(SessionKind::Project, &args.name, &vars, args.prune).exit_err(1);
+    for session in sessions {
+        println!("Synced {session}");
+    }
 }
@@ -0,0 +1,7 @@
+use crate::{cli::restart::RestartArgs, exit::ExitErr};
+use moxide::session::restart_session;
+
+pub fn restart_handler(args: RestartArgs) {
+    restart_session(&args.name, &args.vars.into_iter().collect()).exit_err(1);
+    println!("Restarted {}", args.name);
+}
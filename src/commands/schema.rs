@@ -0,0 +1,7 @@
+use crate::exit::Exit;
+use moxide::schema::SchemaKind;
+
+pub fn schema_handler(kind: SchemaKind) {
+    let schema = serde_json::to_string_pretty(&kind.schema()).exit(1, "Can't serialize the schema");
+    println!("{schema}");
+}
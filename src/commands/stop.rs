@@ -0,0 +1,18 @@
+use crate::{cli::stop::StopArgs, exit::ExitErr};
+use moxide::session::{stop_sessions, stop_targets};
+use std::time::Duration;
+
+pub fn stop_handler(args: StopArgs) {
+    let targets = stop_targets(args.name.as_deref(), args.kind).exit_err(1);
+    if targets.is_empty() {
+        println!("No sessions to stop");
+        return;
+    }
+
+    let graceful = args.graceful.then(|| Duration::from_secs(args.timeout));
+    stop_sessions(&targets, graceful).exit_err(1);
+
+    for target in targets {
+        println!("Stopped {target}");
+    }
+}
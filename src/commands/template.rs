@@ -1,23 +1,31 @@
 use crate::{
-    cli::template::{StartTemplateArgs, TemplateCommands},
+    cli::{
+        sync::SyncArgs,
+        template::{StartTemplateArgs, TemplateCommands},
+    },
+    exit::{Exit, ExitErr},
+};
+use moxide::{
     directories::parse_directory_config,
-    helpers::{absolute_path, apply_if_some, dir_name, Exit, ExitErr},
-    templates::{apply_windows, find_template, parse_template_config},
+    helpers::{absolute_path, dir_name},
+    session::{attach_session, find_session, start_session, SessionKind, SessionTag, StartOptions},
+    sync::sync_sessions,
+    templates::{find_template, parse_template_config},
     tmux,
     widgets::{heading::Heading, table::Table},
 };
 use std::path::PathBuf;
-use tmux_interface::{NewSession, Tmux, TmuxCommand};
 
 pub fn template_handler(action: TemplateCommands) {
     match action {
         TemplateCommands::List { minimal, all } => list_handler(minimal, all),
         TemplateCommands::Start(args) => start_handler(args),
+        TemplateCommands::Sync(args) => sync_handler(args),
     }
 }
 
 fn list_handler(minimal: bool, all: bool) {
-    let templates = parse_template_config();
+    let templates = parse_template_config().exit_err(1);
     let filtered = if all {
         templates
     } else {
@@ -38,9 +46,10 @@ fn list_handler(minimal: bool, all: bool) {
 }
 
 fn start_handler(args: StartTemplateArgs) {
-    let template = find_template(&args.template_name).exit(1, "No template found");
-
-    let detached = args.detached;
+    let template = find_template(&args.template_name).exit_err(1);
+    let windows = template
+        .resolve_windows(&args.vars.into_iter().collect())
+        .exit_err(1);
 
     let resolved_path = args.directory.and_then(|dir| {
         let dirs = parse_directory_config().exit_err(1);
@@ -53,55 +62,32 @@ fn start_handler(args: StartTemplateArgs) {
 
     let name = resolved_path
         .as_ref()
-        .map_or(template.name, |p| dir_name(p));
+        .map_or_else(|| template.name.clone(), |p| dir_name(p));
+    let tag = SessionTag {
+        kind: SessionKind::Template,
+        name: template.name.clone(),
+        root: resolved_path,
+    };
 
-    if tmux::session_exists(&name).unwrap_or(false) && !args.always_new_session {
-        apply_if_some(
-            Tmux::new(),
-            (!detached).then(|| tmux::attach(&name)),
-            |tmux, cmd| tmux.add_command(cmd),
-        )
-        .output()
-        .exit(1, "Could not attach to the Tmux-session");
-        return;
+    if !args.always_new_session {
+        if let Some(session) = find_session(&tag).exit_err(1) {
+            attach_session(&session, args.detached).exit(1, "Could not attach to the Tmux-session");
+            return;
+        }
     }
 
-    let (new_session_cmd, name) = resolve_cmd_name(resolved_path.as_ref(), args.name, name);
-
-    let initial_tmux = apply_if_some(
-        Tmux::new().add_command(new_session_cmd),
-        (!detached).then(|| tmux::attach(&name)),
-        |tmux, cmd| tmux.add_command(cmd),
-    );
-
-    let tmux = apply_windows(initial_tmux, &template.windows, resolved_path.as_ref());
-
-    tmux.output().exit(1, "Could not start Tmux-session");
+    let name = tmux::get_unused_name(&args.name.unwrap_or(name));
+    let options = StartOptions {
+        detached: args.detached,
+        wait: args.wait,
+    };
+    start_session(&name, &tag, &template, &windows, options).exit_err(1);
 }
 
-fn resolve_cmd_name(
-    path: Option<&PathBuf>,
-    name: Option<String>,
-    template_name: String,
-) -> (TmuxCommand<'static>, String) {
-    if let Some(p) = path {
-        let session_name = tmux::get_unused_name(&name.unwrap_or_else(|| dir_name(p)));
-        return (
-            NewSession::new()
-                .detached()
-                .session_name(session_name.clone())
-                .start_directory(p.to_string_lossy().into_owned())
-                .into(),
-            session_name,
-        );
+fn sync_handler(args: SyncArgs) {
+    let vars = args.vars.into_iter().collect();
+    let sessions = sync_sessions(SessionKind::Template, &args.name, &vars, args.prune).exit_err(1);
+    for session in sessions {
+        println!("Synced {session}");
     }
-
-    let session_name = tmux::get_unused_name(&name.unwrap_or(template_name));
-    (
-        NewSession::new()
-            .detached()
-            .session_name(session_name.clone())
-            .into(),
-        session_name,
-    )
 }
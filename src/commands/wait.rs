@@ -0,0 +1,27 @@
+use crate::{
+    cli::wait::{TypeArgs, WaitArgs},
+    exit::ExitErr,
+};
+use moxide::readiness::{type_command, wait_for_prompt, window_pane_output, WaitFor};
+use std::process;
+
+pub fn wait_handler(args: WaitArgs) {
+    let wait = WaitFor {
+        port: args.port,
+        file: args.file,
+        output: args.output,
+        pane: args.pane,
+        timeout: Some(args.timeout),
+    };
+
+    wait.wait(None, || window_pane_output(args.pane?))
+        .exit_err(1);
+}
+
+pub fn type_handler(args: TypeArgs) {
+    // Runs in the background of tmux, so a slow shell only delays the command
+    wait_for_prompt(&args.pane, args.timeout);
+    if !type_command(&args.pane, &args.command_line) {
+        process::exit(1);
+    }
+}
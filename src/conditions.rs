@@ -0,0 +1,60 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A condition for windows and panes, all given checks have to pass
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// A file or directory that has to exist, relative to the root of the session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exists: Option<PathBuf>,
+    /// An environment variable that has to be set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// The name the host has to have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// A command that has to be on the `PATH`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+impl Condition {
+    pub fn is_met(&self, root: Option<&Path>) -> bool {
+        let exists = self.exists.as_ref().is_none_or(|path| {
+            root.map_or_else(|| path.to_path_buf(), |root| root.join(path))
+                .exists()
+        });
+        let env = self
+            .env
+            .as_ref()
+            .is_none_or(|name| env::var_os(name).is_some());
+        let hostname = self
+            .hostname
+            .as_ref()
+            .is_none_or(|name| hostname().is_some_and(|host| &host == name));
+        let command = self.command.as_ref().is_none_or(|cmd| is_on_path(cmd));
+
+        exists && env && hostname && command
+    }
+}
+
+fn hostname() -> Option<String> {
+    let from_proc = fs::read_to_string("/proc/sys/kernel/hostname").ok();
+    let name = from_proc.or_else(|| {
+        let output = Command::new("uname").arg("-n").output().ok()?;
+        String::from_utf8(output.stdout).ok()
+    })?;
+
+    Some(name.trim().to_string())
+}
+
+fn is_on_path(command: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(command).is_file()))
+}
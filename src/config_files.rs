@@ -0,0 +1,221 @@
+use crate::formats::{Format, FormatError};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+use thiserror::Error;
+
+/// Receives the warnings, they are dropped until a handler is set
+static WARNING_HANDLER: OnceLock<fn(&str)> = OnceLock::new();
+/// Warnings that were already reported, a broken file is found by every lookup
+static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// A config file somewhere below a config directory
+#[derive(Debug)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    /// The subdirectories the file is in, joined with `/`
+    pub namespace: Option<String>,
+}
+
+impl ConfigFile {
+    /// Prefixes the name with the namespace of the file
+    pub fn qualify(&self, name: &str) -> String {
+        qualify(self.namespace.as_deref(), name)
+    }
+
+    /// Parses the file, `None` with a warning if it can't be read or isn't a valid config
+    pub fn parse<T: DeserializeOwned>(&self) -> Option<T> {
+        self.try_parse().map_err(warn).ok()
+    }
+
+    pub fn try_parse<T: DeserializeOwned>(&self) -> Result<T, ConfigFileError> {
+        let content = fs::read_to_string(&self.path).map_err(|source| ConfigFileError::Read {
+            path: self.path.clone(),
+            source,
+        })?;
+
+        Format::of_file(&self.path)
+            .parse(&content)
+            .map_err(|source| ConfigFileError::Format {
+                path: self.path.clone(),
+                location: source.location(&content),
+                source: Box::new(source),
+            })
+    }
+
+    /// The name of the file without its extension
+    pub fn stem(&self) -> Option<&str> {
+        self.path.file_stem().and_then(|stem| stem.to_str())
+    }
+
+    /// Whether the file is named like the config that is searched for
+    fn is_named(&self, query: &str) -> bool {
+        self.stem()
+            .is_some_and(|stem| name_matches(&self.qualify(stem), query))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigFileError {
+    #[error("{}: Can't read the file: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("{}: {source}", position(path, *location))]
+    Format {
+        path: PathBuf,
+        location: Option<(usize, usize)>,
+        source: Box<FormatError>,
+    },
+}
+
+/// The path with the line and column, like compilers print it
+fn position(path: &Path, location: Option<(usize, usize)>) -> String {
+    match location {
+        Some((line, column)) => format!("{}:{line}:{column}", path.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Sets the function that receives warnings about configs moxide skips,
+/// like files that can't be parsed. Returns false if a handler was already set.
+pub fn on_warning(handler: fn(&str)) -> bool {
+    WARNING_HANDLER.set(handler).is_ok()
+}
+
+/// Passes the warning to the handler, every warning is only reported once
+pub(crate) fn warn<M: Display>(message: M) {
+    let Some(handler) = WARNING_HANDLER.get() else {
+        return;
+    };
+
+    let message = message.to_string();
+    let is_new = WARNED
+        .lock()
+        .map_or(true, |mut warned| warned.insert(message.clone()));
+    if is_new {
+        handler(&message);
+    }
+}
+
+/// Prefixes the name with the namespace, like `work/api`
+pub fn qualify(namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(namespace) => format!("{namespace}/{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// Whether `name` is `query`, either with or without its namespace
+pub fn name_matches(name: &str, query: &str) -> bool {
+    name == query || name.ends_with(&format!("/{query}"))
+}
+
+/// All files below `dir`, hidden entries are skipped.
+/// Symlinks are followed, but every file and directory is only visited once.
+pub fn config_files(dir: &Path) -> io::Result<Vec<ConfigFile>> {
+    let mut visited = HashSet::from([dir.canonicalize()?]);
+    let mut files = Vec::new();
+    collect(dir, None, &mut visited, &mut files)?;
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Like `config_files`, but files named like `query` come first
+pub fn config_files_for(dir: &Path, query: &str) -> io::Result<Vec<ConfigFile>> {
+    let mut files = config_files(dir)?;
+    files.sort_by_key(|file| !file.is_named(query));
+    Ok(files)
+}
+
+fn collect(
+    dir: &Path,
+    namespace: Option<&str>,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<ConfigFile>,
+) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.filter_map(Result::ok).collect();
+    // Sorted, so the same one of several links to a file is used every time
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        // Broken symlinks can't be canonicalized and are skipped
+        let Ok(canonical) = path.canonicalize() else {
+            continue;
+        };
+        if file_name.starts_with('.') || !visited.insert(canonical) {
+            continue;
+        }
+
+        if path.is_file() {
+            files.push(ConfigFile {
+                path,
+                namespace: namespace.map(str::to_string),
+            });
+        } else if path.is_dir() {
+            let nested = match namespace {
+                Some(namespace) => format!("{namespace}/{file_name}"),
+                None => file_name,
+            };
+            // A subdirectory that can't be read shouldn't hide the other configs
+            let _ = collect(&path, Some(&nested), visited, files);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::TempDir;
+
+    #[test]
+    fn test_config_files() {
+        let root = TempDir::new("config-files");
+        fs::create_dir_all(root.join("work/backend")).unwrap();
+        fs::write(root.join("Home.yaml"), "").unwrap();
+        fs::write(root.join("work/backend/Api.yaml"), "").unwrap();
+        fs::write(root.join(".hidden.yaml"), "").unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&*root, root.join("work/loop")).unwrap();
+            std::os::unix::fs::symlink(root.join("Home.yaml"), root.join("Link.yaml")).unwrap();
+        }
+
+        let files = config_files(&root).unwrap();
+        let names: Vec<_> = files.iter().map(|file| file.qualify("x")).collect();
+        assert_eq!(names, ["x", "work/backend/x"]);
+
+        let files = config_files_for(&root, "backend/Api").unwrap();
+        assert!(files[0].path.ends_with("Api.yaml"));
+    }
+
+    #[test]
+    fn test_warn_once() {
+        static RECEIVED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+        on_warning(|message| RECEIVED.lock().unwrap().push(message.to_string()));
+
+        warn("test_warn_once");
+        warn("test_warn_once");
+        let received = RECEIVED.lock().unwrap();
+        assert_eq!(
+            received.iter().filter(|m| *m == "test_warn_once").count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_name_matches() {
+        assert!(name_matches("work/api", "api"));
+        assert!(name_matches("work/api", "work/api"));
+        assert!(!name_matches("work/api", "work"));
+        assert!(!name_matches("work/myapi", "api"));
+    }
+}
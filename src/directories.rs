@@ -1,13 +1,18 @@
 use thiserror::Error;
 
 use crate::{
-    helpers::{get_config_dir, Exit},
+    formats::{Format, FormatError},
+    helpers::get_config_dir,
     widgets::table::Table,
+    Error,
 };
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::Deserialize;
 use std::{
-    collections::{hash_map::Keys, HashMap},
-    fs,
-    path::PathBuf,
+    borrow::Cow,
+    collections::{hash_map::Keys, BTreeMap, HashMap},
+    fmt, fs, io,
+    path::{Path, PathBuf},
 };
 
 #[derive(Debug, Default, Clone)]
@@ -18,7 +23,7 @@ impl Directories {
         self.0.get(name)
     }
 
-    pub fn names(&self) -> Keys<String, PathBuf> {
+    pub fn names(&self) -> Keys<'_, String, PathBuf> {
         self.0.keys()
     }
 }
@@ -50,32 +55,82 @@ pub enum ParseDirectoryError {
         name: String,
         values: (PathBuf, PathBuf),
     },
+    #[error("Can't read the directories file {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("{} is not a valid directories file: {source}", path.display())]
+    Format { path: PathBuf, source: FormatError },
 }
 
-pub fn parse_directory_config() -> Result<Directories, ParseDirectoryError> {
-    let file_content = fs::read_to_string(get_config_dir().join("directories.yaml"))
-        .exit(1, "Can't read directories config file");
+/// The forms the directories file can have
+enum RawDirectories {
+    /// `name: path` entries
+    Map(BTreeMap<String, PathBuf>),
+    /// A list of paths or `{name, path}` maps
+    List(Vec<RawDirectory>),
+}
 
-    let mut hm = HashMap::new();
+enum RawDirectory {
+    Path(PathBuf),
+    Named { name: Option<String>, path: PathBuf },
+}
 
-    for line in file_content.lines() {
-        if line.starts_with('#') {
-            // Comments
-            continue;
-        }
+/// The directories file, written in any of the supported formats
+pub fn directories_file() -> Result<PathBuf, Error> {
+    Ok(directories_file_in(&get_config_dir()?))
+}
+
+/// The directories file in `config_dir`
+pub fn directories_file_in(config_dir: &Path) -> PathBuf {
+    let candidates = Format::ALL
+        .iter()
+        .map(|format| config_dir.join(format!("directories.{}", format.extension())));
+
+    candidates
+        .chain([config_dir.join("directories.yml")])
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| config_dir.join("directories.yaml"))
+}
 
-        let (name, dir) = line.split_once(':').map_or_else(
-            || {
-                let path = PathBuf::from(line.trim());
-                let name = path
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .ok_or_else(|| ParseDirectoryError::NoDirName { dir: path.clone() })?;
-                Ok((name.to_string(), path))
-            },
-            |(name, path)| Ok((name.trim().to_string(), PathBuf::from(path.trim()))),
-        )?;
+pub fn parse_directory_config() -> Result<Directories, Error> {
+    let path = directories_file()?;
+    let file_content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(source) => return Err(ParseDirectoryError::Read { path, source }.into()),
+    };
 
+    Ok(parse_directories(&file_content, &path)?)
+}
+
+/// Parses the content of the directories file at `path`, the extension decides the format
+pub fn parse_directories(content: &str, path: &Path) -> Result<Directories, ParseDirectoryError> {
+    let format = Format::of_file(path);
+    let entries = match format.parse::<Option<RawDirectories>>(content) {
+        Ok(None) => Vec::new(),
+        Ok(Some(RawDirectories::Map(map))) => map.into_iter().collect(),
+        Ok(Some(RawDirectories::List(list))) => list
+            .into_iter()
+            .map(|entry| match entry {
+                RawDirectory::Named {
+                    name: Some(name),
+                    path,
+                } => Ok((name, path)),
+                RawDirectory::Named { name: None, path } | RawDirectory::Path(path) => {
+                    Ok((name_of(&path)?, path))
+                }
+            })
+            .collect::<Result<_, _>>()?,
+        // Paths and `name: path` lines can be mixed, which isn't valid YAML
+        Err(_) if format == Format::Yaml && is_line_format(content) => parse_lines(content)?,
+        Err(source) => {
+            return Err(ParseDirectoryError::Format {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+
+    let mut hm = HashMap::new();
+    for (name, dir) in entries {
         // TODO: Remove cloning
         if let Some(prev) = hm.insert(name.clone(), dir.clone()) {
             return Err(ParseDirectoryError::DuplicateName {
@@ -87,3 +142,186 @@ pub fn parse_directory_config() -> Result<Directories, ParseDirectoryError> {
 
     Ok(Directories(hm))
 }
+
+/// The significant lines of the file, without comments
+fn significant_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(|line| line.split_once(" #").map_or(line, |(line, _)| line))
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+}
+
+/// Whether the file has one entry per line instead of YAML lists or nested maps
+fn is_line_format(content: &str) -> bool {
+    significant_lines(content).all(|line| {
+        !line.starts_with(char::is_whitespace)
+            && !["-", "[", "{"].iter().any(|c| line.starts_with(c))
+    })
+}
+
+/// Parses lines that are either a path or `name: path`
+fn parse_lines(content: &str) -> Result<Vec<(String, PathBuf)>, ParseDirectoryError> {
+    significant_lines(content)
+        .map(|line| match line.split_once(": ") {
+            Some((name, path)) => Ok((unquote(name).to_string(), PathBuf::from(unquote(path)))),
+            None => {
+                let path = PathBuf::from(unquote(line));
+                Ok((name_of(&path)?, path))
+            }
+        })
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+fn name_of(path: &Path) -> Result<String, ParseDirectoryError> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| ParseDirectoryError::NoDirName {
+            dir: path.to_path_buf(),
+        })
+}
+
+impl<'de> Deserialize<'de> for RawDirectories {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct DirectoriesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for DirectoriesVisitor {
+            type Value = RawDirectories;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a list of directories or a map of names to paths")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(serde::de::value::SeqAccessDeserializer::new(seq))
+                    .map(RawDirectories::List)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<Self::Value, A::Error> {
+                BTreeMap::deserialize(serde::de::value::MapAccessDeserializer::new(map))
+                    .map(RawDirectories::Map)
+            }
+        }
+
+        deserializer.deserialize_any(DirectoriesVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawDirectory {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct RawNamed {
+            name: Option<String>,
+            path: PathBuf,
+        }
+
+        struct DirectoryVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for DirectoryVisitor {
+            type Value = RawDirectory;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a path or a mapping with a name and a path")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, path: &str) -> Result<Self::Value, E> {
+                Ok(RawDirectory::Path(PathBuf::from(path)))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let raw = RawNamed::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                Ok(RawDirectory::Named {
+                    name: raw.name,
+                    path: raw.path,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(DirectoryVisitor)
+    }
+}
+
+impl JsonSchema for Directories {
+    fn schema_name() -> Cow<'static, str> {
+        "Directories".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let path = json_schema!({ "type": "string" });
+        let named = json_schema!({
+            "type": "object",
+            "properties": { "name": path, "path": path },
+            "required": ["path"],
+            "additionalProperties": false,
+        });
+
+        json_schema!({
+            "description": "A list of directories, a map of names to paths or one path per line",
+            "anyOf": [
+                { "type": "array", "items": { "anyOf": [path, named] } },
+                { "type": "object", "additionalProperties": path },
+                { "type": "string" },
+                { "type": "null" },
+            ],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_directories() {
+        let path = Path::new("directories.yaml");
+        let forms = [
+            "- name: \"Downloads\"\n  path: \"~/Downloads/\"\n- ~/src/moxide",
+            "Downloads: ~/Downloads/\nmoxide: ~/src/moxide # comment",
+            "# comment\n~/Downloads/\n\n~/src/moxide",
+            "~/Downloads/ # comment\nmoxide: \"~/src/moxide\"",
+        ];
+        for form in forms {
+            let dirs = parse_directories(form, path).unwrap();
+            assert_eq!(dirs.get("Downloads"), Some(&PathBuf::from("~/Downloads/")));
+            assert_eq!(dirs.get("moxide"), Some(&PathBuf::from("~/src/moxide")));
+        }
+
+        let dirs = parse_directories("db: /mnt/c:/data", path).unwrap();
+        assert_eq!(dirs.get("db"), Some(&PathBuf::from("/mnt/c:/data")));
+        let dirs = parse_directories("/mnt/c:/data\nsrc: ~/src", path).unwrap();
+        assert_eq!(dirs.get("data"), Some(&PathBuf::from("/mnt/c:/data")));
+        assert_eq!(dirs.get("src"), Some(&PathBuf::from("~/src")));
+        assert!(parse_directories("", path)
+            .unwrap()
+            .names()
+            .next()
+            .is_none());
+
+        let err = parse_directories("- name: a\n  path: /a\n- nme: b", path).unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{err}");
+    }
+}
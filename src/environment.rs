@@ -0,0 +1,108 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+pub type Environment = BTreeMap<String, String>;
+
+#[derive(Debug, Error)]
+pub enum EnvironmentError {
+    #[error("Can't read the env file {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Line {line} of the env file {} isn't in the KEY=VALUE format", path.display())]
+    InvalidLine { path: PathBuf, line: usize },
+}
+
+/// Parses the content of a dotenv file, the error contains the (one based) line that is invalid
+pub fn parse_dotenv(content: &str) -> Result<Environment, usize> {
+    let mut env = Environment::new();
+
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').ok_or(idx + 1)?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(idx + 1);
+        }
+
+        env.insert(key.to_string(), unquote(value.trim()).to_string());
+    }
+
+    Ok(env)
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+
+    // Unquoted values can have a trailing comment
+    value
+        .split_once(" #")
+        .map_or(value, |(value, _)| value.trim_end())
+}
+
+/// Combines the variables from `env_file` (relative to `root`) with `env`.
+/// Variables in `env` take precedence over the ones from the file.
+pub fn load_environment(
+    env: &Environment,
+    env_file: Option<&Path>,
+    root: Option<&Path>,
+) -> Result<Environment, EnvironmentError> {
+    let mut loaded = match env_file {
+        Some(file) => {
+            let path = root.map_or_else(|| file.to_path_buf(), |root| root.join(file));
+            let content = fs::read_to_string(&path).map_err(|source| EnvironmentError::Read {
+                path: path.clone(),
+                source,
+            })?;
+            parse_dotenv(&content).map_err(|line| EnvironmentError::InvalidLine { path, line })?
+        }
+        None => Environment::new(),
+    };
+    loaded.extend(env.clone());
+
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let env = parse_dotenv(
+            "# Database
+export DATABASE_URL=\"postgres://localhost/app\"
+PORT=3000 # the dev server
+
+NAME='my app'",
+        )
+        .unwrap();
+
+        assert_eq!(
+            env,
+            Environment::from([
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://localhost/app".to_string()
+                ),
+                ("PORT".to_string(), "3000".to_string()),
+                ("NAME".to_string(), "my app".to_string()),
+            ])
+        );
+        assert_eq!(parse_dotenv("FOO=bar\nnot a variable"), Err(2));
+    }
+}
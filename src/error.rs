@@ -0,0 +1,60 @@
+use crate::{
+    config_files::ConfigFileError,
+    directories::ParseDirectoryError,
+    environment::EnvironmentError,
+    freeze::FreezeError,
+    hooks::HookError,
+    index::IndexError,
+    projects::ProjectError,
+    readiness::WaitError,
+    session::SessionError,
+    templates::{TemplateError, WindowError},
+};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Everything that can go wrong in moxide
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Could not find a config directory")]
+    NoConfigDir,
+    #[error("Can't read the config directory {}: {source}", path.display())]
+    ReadConfigDir { path: PathBuf, source: io::Error },
+    #[error(transparent)]
+    ConfigFile(#[from] ConfigFileError),
+    #[error(transparent)]
+    Index(#[from] IndexError),
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+    #[error(transparent)]
+    Window(#[from] WindowError),
+    #[error(transparent)]
+    Project(#[from] ProjectError),
+    #[error(transparent)]
+    Directories(#[from] ParseDirectoryError),
+    #[error(transparent)]
+    Environment(#[from] EnvironmentError),
+    #[error(transparent)]
+    Hook(#[from] HookError),
+    #[error(transparent)]
+    Wait(#[from] WaitError),
+    #[error(transparent)]
+    Freeze(#[from] FreezeError),
+    #[error(transparent)]
+    Session(#[from] SessionError),
+    #[error("tmux could not be run: {0}")]
+    Tmux(#[from] tmux_interface::Error),
+}
+
+impl Error {
+    /// Turns an error of reading the config directory at `path` into an `Error`
+    pub fn read_config_dir(path: &Path) -> impl FnOnce(io::Error) -> Self + '_ {
+        |source| Self::ReadConfigDir {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use clap::ValueEnum;
+use serde::{
+    de::{self, DeserializeOwned, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{collections::BTreeMap, fmt, path::Path};
+use thiserror::Error;
+
+/// A file format configs can be written in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
+
+#[derive(Debug, Error)]
+pub enum FormatError {
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error(transparent)]
+    TomlDe(#[from] toml::de::Error),
+    #[error(transparent)]
+    TomlSer(#[from] toml::ser::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl Format {
+    pub const ALL: [Self; 3] = [Self::Yaml, Self::Toml, Self::Json];
+
+    /// The format of a file with that extension, `None` for unknown extensions
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// The format of a file, files with an unknown extension are read as YAML
+    pub fn of_file(path: &Path) -> Self {
+        Self::from_extension(path).unwrap_or_default()
+    }
+
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> Result<T, FormatError> {
+        Ok(match self {
+            Self::Yaml => serde_yaml::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+            Self::Json => serde_json::from_str(content)?,
+        })
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, FormatError> {
+        Ok(match self {
+            Self::Yaml => serde_yaml::to_string(value)?,
+            Self::Toml => toml::to_string(value)?,
+            Self::Json => serde_json::to_string_pretty(value)?,
+        })
+    }
+}
+
+impl FormatError {
+    /// The line and column the error occurred at, both starting at 1
+    pub fn location(&self, content: &str) -> Option<(usize, usize)> {
+        match self {
+            Self::Yaml(err) => err
+                .location()
+                .map(|location| (location.line(), location.column())),
+            Self::TomlDe(err) => err.span().map(|span| line_and_column(content, span.start)),
+            Self::Json(err) => (err.line() > 0).then(|| (err.line(), err.column())),
+            Self::TomlSer(_) => None,
+        }
+    }
+}
+
+fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |idx| idx + 1) + 1;
+    (line, column)
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Yaml => write!(f, "YAML"),
+            Self::Toml => write!(f, "TOML"),
+            Self::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// A string that can be written as a number or boolean as well.
+/// YAML reads all of them as strings, TOML and JSON keep their types.
+struct Scalar(String);
+
+impl<'de> Deserialize<'de> for Scalar {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ScalarVisitor)
+    }
+}
+
+struct ScalarVisitor;
+
+impl Visitor<'_> for ScalarVisitor {
+    type Value = Scalar;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string, number or boolean")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+}
+
+/// Reads a map of strings whose values can be numbers or booleans, see `scalars_schema`
+pub fn scalar_map<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let map = BTreeMap::<String, Scalar>::deserialize(deserializer)?;
+    Ok(map.into_iter().map(|(key, value)| (key, value.0)).collect())
+}
+
+/// Like `scalar_map`, but values can be left empty
+pub fn optional_scalar_map<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Option<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let map = BTreeMap::<String, Option<Scalar>>::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .map(|(key, value)| (key, value.map(|value| value.0)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projects::Project;
+
+    #[test]
+    fn test_formats() {
+        let yaml = "name: Api
+root_dir: ~/api
+windows:
+  - name: Editor
+    panes: [nvim]";
+        let toml = r#"name = "Api"
+root_dir = "~/api"
+
+[[windows]]
+name = "Editor"
+panes = ["nvim"]"#;
+        let json = r#"{"name": "Api", "root_dir": "~/api", "windows": [{"name": "Editor", "panes": ["nvim"]}]}"#;
+
+        let project: Project = Format::Yaml.parse(yaml).unwrap();
+        assert_eq!(Format::Toml.parse::<Project>(toml).unwrap(), project);
+        assert_eq!(Format::Json.parse::<Project>(json).unwrap(), project);
+
+        for format in Format::ALL {
+            let serialized = format.serialize(&project).unwrap();
+            assert_eq!(format.parse::<Project>(&serialized).unwrap(), project);
+        }
+
+        // YAML reads numbers and booleans as strings, the other formats have to convert them
+        let yaml = "name: Api
+root_dir: ~/api
+vars: { port: 3000 }
+env: { DEBUG: true }
+options: { mouse: true }
+windows:
+  - name: Editor
+    options: { pane-base-index: 1 }
+    panes:
+      - command: nvim
+        env: { RATIO: 1.5 }";
+        let toml = r#"name = "Api"
+root_dir = "~/api"
+vars = { port = 3000 }
+env = { DEBUG = true }
+options = { mouse = true }
+
+[[windows]]
+name = "Editor"
+options = { pane-base-index = 1 }
+panes = [{ command = "nvim", env = { RATIO = 1.5 } }]"#;
+        let json = r#"{"name": "Api", "root_dir": "~/api", "vars": {"port": 3000},
+            "env": {"DEBUG": true}, "options": {"mouse": true}, "windows": [{"name": "Editor",
+            "options": {"pane-base-index": 1}, "panes": [{"command": "nvim", "env": {"RATIO": 1.5}}]}]}"#;
+
+        let project: Project = Format::Yaml.parse(yaml).unwrap();
+        assert_eq!(Format::Toml.parse::<Project>(toml).unwrap(), project);
+        assert_eq!(Format::Json.parse::<Project>(json).unwrap(), project);
+        let template = project.into_template().unwrap();
+        assert_eq!(template.vars["port"].as_deref(), Some("3000"));
+        assert_eq!(template.options["mouse"], "true");
+        assert_eq!(template.windows[0].panes[0].env["RATIO"], "1.5");
+
+        assert_eq!(
+            Format::from_extension(Path::new("api.TOML")),
+            Some(Format::Toml)
+        );
+    }
+}
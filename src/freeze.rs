@@ -0,0 +1,282 @@
+use crate::{
+    config_files::warn,
+    environment::Environment,
+    helpers::apply_if_some,
+    projects::{Project, ProjectSetup},
+    templates::{Options, Pane as TemplatePane, Window as TemplateWindow, WINDOW_OPTION},
+    variables::Variables,
+};
+use itertools::Itertools;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tmux_interface::{Tmux, TmuxCommand};
+
+const SEPERATOR: char = ':';
+
+/// A window of a running session
+#[derive(Debug)]
+pub struct Window {
+    pub index: usize,
+    pub name: String,
+    pub layout: String,
+    /// The index of the window in its config, only set on windows moxide created
+    pub config_index: Option<usize>,
+    /// The current directories of the panes
+    pub panes: Vec<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum FreezeError {
+    #[error("Can't receive current tmux session setup")]
+    ListWindows,
+    #[error("The session has no panes")]
+    NoPanes,
+}
+
+impl Window {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_template_window(self, most_used_path: &Path) -> TemplateWindow {
+        // When all panes share a directory it becomes the directory of the window
+        let window_dir = self
+            .panes
+            .iter()
+            .all_equal_value()
+            .ok()
+            .filter(|dir| dir.as_path() != most_used_path)
+            .map(|dir| relative_to(dir, most_used_path));
+
+        let panes = self
+            .panes
+            .iter()
+            .map(|dir| {
+                if window_dir.is_some() || dir == most_used_path {
+                    TemplatePane::default()
+                } else {
+                    TemplatePane {
+                        cwd: Some(relative_to(dir, most_used_path)),
+                        ..TemplatePane::default()
+                    }
+                }
+            })
+            .collect();
+
+        TemplateWindow {
+            panes,
+            name: Some(self.name),
+            layout: Some(self.layout),
+            dir: window_dir,
+            options: Options::new(),
+            split: None,
+            when: None,
+        }
+    }
+}
+
+fn relative_to(dir: &Path, root: &Path) -> PathBuf {
+    dir.strip_prefix(root)
+        .map_or_else(|_| dir.to_path_buf(), Path::to_path_buf)
+}
+
+#[derive(Debug)]
+enum ActiveTmuxInstance {
+    Window {
+        name: String,
+        layout: String,
+        index: usize,
+        session_name: String,
+        config_index: Option<usize>,
+    },
+    Pane {
+        window_index: usize,
+        cwd: PathBuf,
+        session_name: String,
+    },
+}
+
+#[derive(Debug)]
+enum TmuxParseError {
+    NoPrefix,
+    UndefinedPrefix(String),
+    MissingData(usize),
+    NoNumber(String),
+}
+
+impl fmt::Display for TmuxParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoPrefix => write!(f, ""),
+            Self::UndefinedPrefix(prefix) => write!(f, "The prefix {prefix} isn't defined"),
+            Self::MissingData(0) => write!(f, "The first entry is missing"),
+            Self::MissingData(1) => write!(f, "The second entry is missing"),
+            Self::MissingData(index) => write!(f, "The {}th entry is missing", index + 1),
+            Self::NoNumber(string) => write!(f, "{string} isn't a number"),
+        }
+    }
+}
+
+impl std::error::Error for TmuxParseError {}
+
+impl TryFrom<&str> for ActiveTmuxInstance {
+    type Error = TmuxParseError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (indicator, data) = value
+            .split_once(SEPERATOR)
+            .ok_or(TmuxParseError::NoPrefix)?;
+        match indicator {
+            "window" => {
+                let mut parts = data.splitn(5, SEPERATOR);
+                let index: String = parts.next().ok_or(TmuxParseError::MissingData(0))?.into();
+                let index = index.parse().map_err(|_| TmuxParseError::NoNumber(index))?;
+                let session_name = parts.next().ok_or(TmuxParseError::MissingData(1))?.into();
+                let layout = parts.next().ok_or(TmuxParseError::MissingData(2))?.into();
+                let config_index = parts.next().ok_or(TmuxParseError::MissingData(3))?;
+                let config_index = config_index.parse().ok();
+                let name = parts.next().ok_or(TmuxParseError::MissingData(4))?.into();
+                Ok(Self::Window {
+                    name,
+                    layout,
+                    index,
+                    session_name,
+                    config_index,
+                })
+            }
+            "pane" => {
+                let mut parts = data.splitn(3, SEPERATOR);
+                let window_index: String =
+                    parts.next().ok_or(TmuxParseError::MissingData(0))?.into();
+                let window_index = window_index
+                    .parse()
+                    .map_err(|_| TmuxParseError::NoNumber(window_index))?;
+                let session_name = parts.next().ok_or(TmuxParseError::MissingData(1))?.into();
+                let cwd = parts.next().ok_or(TmuxParseError::MissingData(2))?;
+                let cwd = PathBuf::from(cwd);
+                Ok(Self::Pane {
+                    window_index,
+                    cwd,
+                    session_name,
+                })
+            }
+            str => Err(TmuxParseError::UndefinedPrefix(str.into())),
+        }
+    }
+}
+
+/// The windows of the session moxide runs in
+pub fn current_windows() -> Result<Vec<Window>, FreezeError> {
+    list_windows(None)
+}
+
+/// The windows of the running session `name`
+pub fn session_windows(name: &str) -> Result<Vec<Window>, FreezeError> {
+    list_windows(Some(name))
+}
+
+fn list_windows(session: Option<&str>) -> Result<Vec<Window>, FreezeError> {
+    // tmux replaces tabs in the output. Indexes, session names, layouts and the config index
+    // can't include a `:`, the window name and the path, which can, come last.
+    let window_cmd = TmuxCommand::list_windows().format(format!(
+        "window:#{{window_index}}:#{{session_name}}:#{{window_layout}}:#{{{WINDOW_OPTION}}}:#{{window_name}}"
+    ));
+    let window_cmd = apply_if_some(window_cmd, session, |cmd, session| {
+        cmd.target_session(session)
+    });
+    let panes_cmd = TmuxCommand::list_panes()
+        .all()
+        .format("pane:#{window_index}:#{session_name}:#{pane_current_path}");
+
+    let output = Tmux::new()
+        .add_command(window_cmd)
+        .add_command(panes_cmd)
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout()).ok())
+        .ok_or(FreezeError::ListWindows)?;
+
+    let tmux_panes_windows =
+        output
+            .lines()
+            .filter_map(|line| match ActiveTmuxInstance::try_from(line) {
+                Ok(data) => Some(data),
+                Err(err) => {
+                    warn(format!("Parsing of one pane or window failed: {err}"));
+                    None
+                }
+            });
+    let window_map = tmux_panes_windows.fold(BTreeMap::new(), |mut windows_map, instance| {
+        match instance {
+            ActiveTmuxInstance::Window {
+                name,
+                layout,
+                index,
+                session_name,
+                config_index,
+            } => {
+                windows_map.entry((index, session_name)).or_insert(Window {
+                    index,
+                    name,
+                    layout,
+                    config_index,
+                    panes: Vec::new(),
+                });
+            }
+            ActiveTmuxInstance::Pane {
+                window_index,
+                cwd,
+                session_name,
+            } => {
+                if let Some(window) = windows_map.get_mut(&(window_index, session_name)) {
+                    window.panes.push(cwd);
+                }
+            }
+        }
+        windows_map
+    });
+    Ok(window_map.into_values().collect())
+}
+
+/// Turns the current session into a project rooted in the most used directory,
+/// the project is named after that directory if no name is given
+pub fn freeze_session(name: Option<String>) -> Result<Project, FreezeError> {
+    let windows = current_windows()?;
+    let most_used_path = windows
+        .iter()
+        .flat_map(|window| &window.panes)
+        .counts()
+        .into_iter()
+        .max_by_key(|&(_, count)| count)
+        .ok_or(FreezeError::NoPanes)?
+        .0
+        .clone();
+    let most_used_path = most_used_path.as_path();
+    let name = name
+        .or_else(|| {
+            let file_name = most_used_path.file_name()?;
+            let str = file_name.to_str()?;
+            Some(str.to_string())
+        })
+        .unwrap_or_else(|| String::from("Unnamed Project"));
+
+    let template_wins: Vec<_> = windows
+        .into_iter()
+        .map(|win| win.to_template_window(most_used_path))
+        .collect();
+
+    Ok(Project {
+        name,
+        root_dir: most_used_path.to_path_buf(),
+        vars: Variables::new(),
+        env: Environment::new(),
+        env_file: None,
+        on_create: Vec::new(),
+        on_attach: Vec::new(),
+        on_stop: Vec::new(),
+        options: Options::new(),
+        setup: ProjectSetup::Windows {
+            windows: template_wins,
+        },
+    })
+}
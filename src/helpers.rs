@@ -1,12 +1,12 @@
-use crate::exit;
+use crate::Error;
 use std::{
     env, fs,
     path::{Path, PathBuf},
 };
 
-pub fn get_config_dir() -> PathBuf {
+pub fn get_config_dir() -> Result<PathBuf, Error> {
     if let Ok(dir) = env::var("MOXIDE_CONFIG") {
-        return PathBuf::from(dir);
+        return Ok(PathBuf::from(dir));
     }
 
     env::var("XDG_CONFIG_HOME")
@@ -14,8 +14,8 @@ pub fn get_config_dir() -> PathBuf {
         .map(PathBuf::from)
         .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
         .or_else(dirs::config_dir)
-        .exit(1, "Could not find a config directory")
-        .join("moxide")
+        .map(|dir| dir.join("moxide"))
+        .ok_or(Error::NoConfigDir)
 }
 
 fn expand_tilde<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
@@ -44,29 +44,6 @@ pub fn absolute_path(path: &Path) -> std::io::Result<PathBuf> {
     fs::canonicalize(expanded)
 }
 
-pub trait Exit<T> {
-    fn exit<M: std::fmt::Display>(self, code: i32, msg: M) -> T;
-}
-pub trait ExitErr<T> {
-    fn exit_err(self, code: i32) -> T;
-}
-
-impl<T, E> Exit<T> for Result<T, E> {
-    fn exit<M: std::fmt::Display>(self, code: i32, msg: M) -> T {
-        self.unwrap_or_else(|_| exit!(code, "{}", msg))
-    }
-}
-impl<T, E: std::error::Error> ExitErr<T> for Result<T, E> {
-    fn exit_err(self, code: i32) -> T {
-        self.unwrap_or_else(|err| exit!(code, "{}", err))
-    }
-}
-impl<T> Exit<T> for Option<T> {
-    fn exit<M: std::fmt::Display>(self, code: i32, msg: M) -> T {
-        self.unwrap_or_else(|| exit!(code, "{}", msg))
-    }
-}
-
 pub fn runs_in_tmux() -> bool {
     env::var("TMUX").is_ok()
 }
@@ -91,12 +68,55 @@ pub fn apply_if_some<B, D, F: Fn(B, D) -> B>(base: B, data: Option<D>, predicate
     }
 }
 
-#[macro_export]
-macro_rules! exit {
-    ($code:expr, $fmt:expr $(, $args:expr)*) => {{
-        eprintln!($fmt $(, $args)*);
-        std::process::exit($code);
-    }};
+/// Escapes `#` so tmux doesn't expand formats in the value
+pub fn escape_format(value: &str) -> String {
+    value.replace('#', "##")
+}
+
+/// Quotes a value for `sh` and the tmux command parser, both treat single quotes the same way
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// The FNV-1a hash of the bytes. Unlike the hasher of std it is the same for every build
+/// of moxide, so it can name things that outlive a single run.
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// A directory for the files of a test, it is removed when the guard is dropped
+#[cfg(test)]
+pub(crate) struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("moxide-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,158 @@
+use crate::{
+    environment::Environment,
+    helpers::{escape_format, shell_quote, stable_hash},
+};
+use std::{
+    io,
+    path::Path,
+    process::{Command, ExitStatus},
+};
+use thiserror::Error;
+use tmux_interface::{SetHook, TmuxCommand};
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("The hook `{command}` could not be started: {source}")]
+    Spawn { command: String, source: io::Error },
+    #[error("The hook `{command}` failed with {status}")]
+    Failed { command: String, status: ExitStatus },
+}
+
+/// Runs the `on_create` hooks one after another and stops at the first failing one
+pub fn run_on_create(
+    commands: &[String],
+    dir: Option<&Path>,
+    env: &Environment,
+) -> Result<(), HookError> {
+    commands
+        .iter()
+        .try_for_each(|command| run_command(command, dir, env))
+}
+
+/// Runs the `on_stop` hooks like the tmux hook does, a failing hook doesn't stop the others.
+/// The first failure is returned.
+pub fn run_on_stop(commands: &[String], dir: Option<&Path>) -> Result<(), HookError> {
+    let env = Environment::new();
+    commands
+        .iter()
+        .map(|command| run_command(command, dir, &env))
+        .fold(Ok(()), Result::and)
+}
+
+fn run_command(command: &str, dir: Option<&Path>, env: &Environment) -> Result<(), HookError> {
+    let mut process = Command::new("sh");
+    process.arg("-c").arg(command).envs(env);
+    if let Some(dir) = dir {
+        process.current_dir(dir);
+    }
+
+    let status = process.status().map_err(|source| HookError::Spawn {
+        command: command.to_string(),
+        source,
+    })?;
+    if !status.success() {
+        return Err(HookError::Failed {
+            command: command.to_string(),
+            status,
+        });
+    }
+    Ok(())
+}
+
+/// The tmux commands that register the `on_attach` and `on_stop` hooks of a session
+pub fn tmux_hooks(
+    on_attach: &[String],
+    on_stop: &[String],
+    session_name: &str,
+    dir: Option<&Path>,
+) -> Vec<TmuxCommand<'static>> {
+    let mut commands = Vec::new();
+
+    if !on_attach.is_empty() {
+        let hook = run_shell(&shell_script(on_attach, dir), true);
+        // Switching the client into the session doesn't count as attaching in tmux
+        for event in ["client-attached", "client-session-changed"] {
+            let cmd = SetHook::new()
+                .target_session(session_name.to_string())
+                .hook_name(event)
+                .command(hook.clone());
+            commands.push(cmd.into());
+        }
+    }
+
+    if !on_stop.is_empty() {
+        // Hooks of a session are gone once it is closed, so a global hook that only reacts
+        // to this session and removes itself is used
+        let hook_name = on_stop_hook(session_name);
+        let guard = format!(
+            "[ \"#{{hook_session_name}}\" = {} ] || exit 0\ntmux -S \"#{{socket_path}}\" set-hook -gu {}",
+            shell_quote(&escape_format(session_name)),
+            shell_quote(&hook_name),
+        );
+        let script = format!("{guard}\n{}", shell_script(on_stop, dir));
+        // A background job is killed when the server exits after its last session is closed,
+        // tmux waits for a foreground job and keeps serving the clients meanwhile
+        let cmd = SetHook::new()
+            .global()
+            .hook_name(hook_name)
+            .command(run_shell(&script, false));
+        commands.push(cmd.into());
+    }
+
+    commands
+}
+
+/// Removes the global hook that runs the `on_stop` hooks of the session when it is closed
+pub fn remove_on_stop(session_name: &str) -> TmuxCommand<'static> {
+    let cmd = SetHook::new()
+        .global()
+        .unset()
+        .hook_name(on_stop_hook(session_name));
+    cmd.into()
+}
+
+fn on_stop_hook(session_name: &str) -> String {
+    format!("session-closed[{}]", hook_index(session_name))
+}
+
+/// Joins the commands into one script that runs in `dir`,
+/// `#` is escaped because tmux expands formats in the script
+fn shell_script(commands: &[String], dir: Option<&Path>) -> String {
+    let commands = commands.join("\n");
+    let script = match dir {
+        Some(dir) => format!(
+            "cd {} || exit 1\n{commands}",
+            shell_quote(&dir.to_string_lossy())
+        ),
+        None => commands,
+    };
+    escape_format(&script)
+}
+
+fn run_shell(script: &str, background: bool) -> String {
+    let flag = if background { "-b " } else { "" };
+    format!("run-shell {flag}{}", shell_quote(script))
+}
+
+/// The index of the global hook of a session, derived from its name with `stable_hash`
+fn hook_index(session_name: &str) -> u32 {
+    // Leave the low indexes for hooks of the user, tmux rejects indexes above `i32::MAX`
+    const FIRST_INDEX: u32 = 1_000;
+    const INDEXES: u64 = (i32::MAX as u32 - FIRST_INDEX) as u64;
+
+    let hash = stable_hash(session_name.as_bytes());
+    FIRST_INDEX + (hash % INDEXES) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_index() {
+        // The FNV-1a hash of "a" is 0xaf63dc4c8601ec8c
+        assert_eq!(hook_index("a"), 555_525_188);
+        assert_ne!(hook_index("api"), hook_index("web"));
+        assert!(hook_index("api") >= 1_000);
+    }
+}
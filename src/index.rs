@@ -0,0 +1,320 @@
+use crate::{
+    config_files::{config_files, name_matches, qualify, warn, ConfigFile},
+    helpers::stable_hash,
+    Error,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use thiserror::Error;
+
+/// Changes whenever the entries change, so old caches are parsed again
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum IndexError {
+    #[error("{query} is ambiguous, it could be any of {}", candidates.join(", "))]
+    Ambiguous {
+        query: String,
+        candidates: Vec<String>,
+    },
+}
+
+/// A config that can be summarized in the index
+pub trait Indexed: DeserializeOwned {
+    fn name(&self) -> &str;
+
+    fn hidden(&self) -> bool {
+        false
+    }
+
+    fn extends(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// What the index remembers about a config file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    /// The subdirectories the file is in, joined with `/`
+    namespace: Option<String>,
+    /// The name as it is written in the file, `None` if the file can't be parsed
+    config_name: Option<String>,
+    /// The name the config is known by, it is only qualified with the namespace
+    /// if configs in other directories have the same name
+    pub name: Option<String>,
+    /// Why the file can't be parsed
+    pub error: Option<String>,
+    pub hidden: bool,
+    pub extends: Option<String>,
+}
+
+impl IndexEntry {
+    fn parse<T: Indexed>(file: &ConfigFile, modified: Option<SystemTime>) -> Self {
+        let mut entry = Self {
+            path: file.path.clone(),
+            modified,
+            namespace: file.namespace.clone(),
+            config_name: None,
+            name: None,
+            error: None,
+            hidden: false,
+            extends: None,
+        };
+        match file.try_parse::<T>() {
+            Ok(config) => {
+                entry.config_name = Some(config.name().to_string());
+                entry.hidden = config.hidden();
+                entry.extends = config.extends().map(str::to_string);
+            }
+            Err(err) => entry.error = Some(err.to_string()),
+        }
+        entry
+    }
+
+    /// The name with the namespace of the file
+    fn qualified_name(&self) -> Option<String> {
+        let name = self.config_name.as_deref()?;
+        Some(qualify(self.namespace.as_deref(), name))
+    }
+
+    /// Whether the name of the file, with its namespace, matches `query`
+    fn is_file_named(&self, query: &str) -> bool {
+        self.path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| name_matches(&qualify(self.namespace.as_deref(), stem), query))
+    }
+
+    /// Parses the file again, the name of the config isn't qualified
+    pub fn load<T: Indexed>(&self) -> Result<T, Error> {
+        let file = ConfigFile {
+            path: self.path.clone(),
+            namespace: None,
+        };
+        file.try_parse().map_err(Error::from)
+    }
+}
+
+/// The names of all configs in a directory, cached on disk.
+/// Only files that changed since the index was written are parsed again.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Index {
+    /// Caches written by other versions of the index are ignored
+    #[serde(default)]
+    version: u32,
+    dir: PathBuf,
+    entries: Vec<IndexEntry>,
+}
+
+impl Index {
+    /// The up to date index of `dir`
+    pub fn of<T: Indexed>(dir: &Path) -> Result<Self, Error> {
+        Self::build::<T>(dir, cache_path(dir).as_deref())
+    }
+
+    fn build<T: Indexed>(dir: &Path, cache: Option<&Path>) -> Result<Self, Error> {
+        let files = config_files(dir).map_err(Error::read_config_dir(dir))?;
+        let cached = cache
+            .and_then(|cache| fs::read_to_string(cache).ok())
+            .and_then(|content| serde_json::from_str::<Self>(&content).ok())
+            .filter(|cached| cached.version == INDEX_VERSION && cached.dir == dir)
+            .unwrap_or_default();
+
+        let mut entries: Vec<_> = files
+            .iter()
+            .map(|file| {
+                let modified = fs::metadata(&file.path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+                cached
+                    .entries
+                    .iter()
+                    .find(|entry| entry.path == file.path)
+                    .filter(|entry| modified.is_some() && entry.modified == modified)
+                    .cloned()
+                    .unwrap_or_else(|| IndexEntry::parse::<T>(file, modified))
+            })
+            .collect();
+        assign_names(&mut entries);
+
+        let index = Self {
+            version: INDEX_VERSION,
+            dir: dir.to_path_buf(),
+            entries,
+        };
+        if cached.version != INDEX_VERSION || index.entries != cached.entries {
+            if let Some(cache) = cache {
+                // The index is only a cache, the configs work without it
+                let _ = index.write(cache);
+            }
+        }
+        Ok(index)
+    }
+
+    fn write(&self, cache: &Path) -> std::io::Result<()> {
+        if let Some(parent) = cache.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(cache, serde_json::to_string(self)?)
+    }
+
+    /// All files that could be parsed
+    pub fn configs(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.entries.iter().filter(|entry| entry.name.is_some())
+    }
+
+    /// Warns about every file that can't be parsed, for commands that list all configs
+    pub fn warn_broken(&self) {
+        for error in self.entries.iter().filter_map(|entry| entry.error.as_ref()) {
+            warn(error);
+        }
+    }
+
+    /// The config named `query`, the namespace can be left out if the name is unique
+    pub fn find(&self, query: &str) -> Result<Option<&IndexEntry>, IndexError> {
+        let candidates: Vec<_> = self
+            .configs()
+            .filter(|entry| {
+                entry
+                    .qualified_name()
+                    .is_some_and(|name| name_matches(&name, query))
+            })
+            .collect();
+        let exact: Vec<_> = candidates
+            .iter()
+            .filter(|entry| entry.name.as_deref() == Some(query))
+            .copied()
+            .collect();
+
+        match (candidates.as_slice(), exact.as_slice()) {
+            ([], _) => Ok(None),
+            ([entry], _) | (_, [entry]) => Ok(Some(entry)),
+            _ => Err(IndexError::Ambiguous {
+                query: query.to_string(),
+                candidates: candidates
+                    .iter()
+                    .filter_map(|entry| entry.name.clone())
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Loads the config named `query` with the name it is known by
+    /// Fails with the parse error if the config, or a file named like it, can't be parsed
+    pub fn load<T: Indexed>(&self, query: &str) -> Result<Option<(String, T)>, Error> {
+        let Some(entry) = self.find(query)? else {
+            let broken = self
+                .entries
+                .iter()
+                .filter(|entry| entry.error.is_some())
+                .find(|entry| entry.is_file_named(query));
+            if let Some(entry) = broken {
+                // Parsing it again returns the error instead of its description
+                entry.load::<T>()?;
+            }
+            return Ok(None);
+        };
+
+        let config = entry.load()?;
+        Ok(Some((entry.name.clone().unwrap_or_default(), config)))
+    }
+}
+
+/// Names every entry by the name in its file, qualified if another entry has the same name
+fn assign_names(entries: &mut [IndexEntry]) {
+    let config_names: Vec<_> = entries.iter().map(|e| e.config_name.clone()).collect();
+    for entry in entries {
+        let is_shared = config_names
+            .iter()
+            .filter(|name| name.is_some() && **name == entry.config_name)
+            .count()
+            > 1;
+        entry.name = if is_shared {
+            entry.qualified_name()
+        } else {
+            entry.config_name.clone()
+        };
+    }
+}
+
+/// The file the index of `dir` is cached in, every config directory gets its own file
+fn cache_path(dir: &Path) -> Option<PathBuf> {
+    let hash = stable_hash(dir.as_os_str().as_encoded_bytes());
+    let file_name = format!("index-{hash:x}.json");
+
+    dirs::cache_dir().map(|cache| cache.join("moxide").join(file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{helpers::TempDir, projects::Project};
+    use std::time::Duration;
+
+    #[test]
+    fn test_index() {
+        let root = TempDir::new("index");
+        let dir = root.join("projects");
+        let cache = root.join("index.json");
+        fs::create_dir_all(dir.join("work")).unwrap();
+        fs::write(
+            dir.join("work/Api.yaml"),
+            "{name: Api, root_dir: ~, windows: []}",
+        )
+        .unwrap();
+        fs::write(dir.join("Broken.yaml"), "name: [").unwrap();
+
+        let index = Index::build::<Project>(&dir, Some(&cache)).unwrap();
+        assert!(matches!(
+            index.load::<Project>("Broken"),
+            Err(Error::ConfigFile(_))
+        ));
+        let api = index.find("Api").unwrap().unwrap();
+        assert_eq!(api.name.as_deref(), Some("Api"));
+        assert_eq!(index.find("work/Api").unwrap(), Some(api));
+        assert!(index.entries.iter().any(|entry| entry.error.is_some()));
+        assert!(cache.is_file());
+
+        // Files with an unchanged modification time are taken from the cache
+        let api = fs::File::options()
+            .write(true)
+            .open(dir.join("work/Api.yaml"))
+            .unwrap();
+        let modified = api.metadata().unwrap().modified().unwrap();
+        fs::write(
+            dir.join("work/Api.yaml"),
+            "{name: Web, root_dir: ~, windows: []}",
+        )
+        .unwrap();
+        api.set_modified(modified).unwrap();
+        let index = Index::build::<Project>(&dir, Some(&cache)).unwrap();
+        assert!(index.find("Api").unwrap().is_some());
+
+        api.set_modified(modified + Duration::from_secs(1)).unwrap();
+        let index = Index::build::<Project>(&dir, Some(&cache)).unwrap();
+        assert!(index.find("Api").unwrap().is_none());
+        assert!(index.find("Web").unwrap().is_some());
+
+        // Names used in several directories are qualified and can't be looked up without it
+        fs::create_dir_all(dir.join("home")).unwrap();
+        fs::write(
+            dir.join("home/Web.yaml"),
+            "{name: Web, root_dir: ~, windows: []}",
+        )
+        .unwrap();
+        let index = Index::build::<Project>(&dir, Some(&cache)).unwrap();
+        let web = index.find("home/Web").unwrap().unwrap();
+        assert_eq!(web.name.as_deref(), Some("home/Web"));
+        let err = index.find("Web").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Web is ambiguous, it could be any of home/Web, work/Web"
+        );
+    }
+}
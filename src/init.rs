@@ -5,7 +5,7 @@ use std::{
 };
 
 fn create_config_dir() -> io::Result<PathBuf> {
-    let config_dir = get_config_dir();
+    let config_dir = get_config_dir().map_err(io::Error::other)?;
     fs::create_dir_all(&config_dir)?;
 
     Ok(config_dir)
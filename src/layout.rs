@@ -0,0 +1,303 @@
+use regex::Regex;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, fmt, sync::LazyLock};
+use thiserror::Error;
+
+/// A cell of a layout string, `WxH,X,Y` followed by the pane id if the cell is a pane
+static LAYOUT_CELL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+x\d+,\d+,\d+(,\d+)?").expect("the regex is valid"));
+
+/// The size the layout is computed for, tmux scales it to the real size of the window
+const LAYOUT_WIDTH: usize = 200;
+const LAYOUT_HEIGHT: usize = 50;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// The children are placed next to each other
+    Horizontal,
+    /// The children are placed below each other
+    Vertical,
+}
+
+/// A node of the split tree of a window
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Split {
+    pub direction: Direction,
+    /// Relative sizes of the children, all children have the same size if omitted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sizes: Vec<usize>,
+    pub children: Vec<SplitChild>,
+}
+
+/// Either a pane (written as `pane`) or another split
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitChild {
+    Pane,
+    Split(Split),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SplitError {
+    #[error("A split needs at least one child")]
+    NoChildren,
+    #[error("A split has {children} children but {sizes} sizes")]
+    SizeCount { children: usize, sizes: usize },
+    #[error("The split tree is too deep to fit into a window")]
+    TooSmall,
+    #[error("The split tree of window {window} has {leaves} panes, but {panes} panes are defined")]
+    PaneCount {
+        window: String,
+        leaves: usize,
+        panes: usize,
+    },
+    #[error("Window {window} can't have both a layout and a split")]
+    LayoutAndSplit { window: String },
+    #[error("Panes of window {window} can't have a when condition, its layout has a fixed number of panes")]
+    ConditionalPane { window: String },
+    #[error("The layout of window {window} has {leaves} panes, but {panes} panes are defined")]
+    LayoutPaneCount {
+        window: String,
+        leaves: usize,
+        panes: usize,
+    },
+}
+
+/// The number of panes of a layout string as tmux prints it, `None` for named layouts
+pub fn layout_pane_count(layout: &str) -> Option<usize> {
+    let (checksum, body) = layout.split_once(',')?;
+    if checksum.len() != 4 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    // Panes end with their id while splits continue with `{` or `[`
+    let panes = LAYOUT_CELL
+        .captures_iter(body)
+        .filter(|captures| captures.get(1).is_some())
+        .count();
+    Some(panes)
+}
+
+impl Split {
+    /// The number of panes in the tree
+    pub fn pane_count(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| match child {
+                SplitChild::Pane => 1,
+                SplitChild::Split(split) => split.pane_count(),
+            })
+            .sum()
+    }
+
+    /// Compiles the tree into a tmux layout string
+    pub fn to_layout(&self) -> Result<String, SplitError> {
+        let mut pane_id = 0;
+        let layout = self.layout_cell(
+            Cell {
+                width: LAYOUT_WIDTH,
+                height: LAYOUT_HEIGHT,
+                x: 0,
+                y: 0,
+            },
+            &mut pane_id,
+        )?;
+
+        Ok(format!("{:04x},{layout}", checksum(&layout)))
+    }
+
+    fn layout_cell(&self, cell: Cell, pane_id: &mut usize) -> Result<String, SplitError> {
+        let count = self.children.len();
+        if count == 0 {
+            return Err(SplitError::NoChildren);
+        }
+
+        let sizes = if self.sizes.is_empty() {
+            vec![1; count]
+        } else if self.sizes.len() == count {
+            self.sizes.clone()
+        } else {
+            return Err(SplitError::SizeCount {
+                children: count,
+                sizes: self.sizes.len(),
+            });
+        };
+
+        let length = match self.direction {
+            Direction::Horizontal => cell.width,
+            Direction::Vertical => cell.height,
+        };
+        // Neighbouring children are separated by a border of one cell
+        let available = length.checked_sub(count - 1).ok_or(SplitError::TooSmall)?;
+        let lengths = distribute(available, &sizes)?;
+
+        let mut offset = 0;
+        let mut children = Vec::with_capacity(count);
+        for (child, child_length) in self.children.iter().zip(lengths) {
+            let child_cell = match self.direction {
+                Direction::Horizontal => Cell {
+                    width: child_length,
+                    x: cell.x + offset,
+                    ..cell
+                },
+                Direction::Vertical => Cell {
+                    height: child_length,
+                    y: cell.y + offset,
+                    ..cell
+                },
+            };
+            offset += child_length + 1;
+
+            let layout = match child {
+                SplitChild::Pane => {
+                    let layout = format!("{child_cell},{pane_id}");
+                    *pane_id += 1;
+                    layout
+                }
+                SplitChild::Split(split) => split.layout_cell(child_cell, pane_id)?,
+            };
+            children.push(layout);
+        }
+
+        let (open, close) = match self.direction {
+            Direction::Horizontal => ('{', '}'),
+            Direction::Vertical => ('[', ']'),
+        };
+        Ok(format!("{cell}{open}{}{close}", children.join(",")))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{},{},{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Splits `available` proportionally to `sizes`, the last entry gets the rest
+fn distribute(available: usize, sizes: &[usize]) -> Result<Vec<usize>, SplitError> {
+    let total: usize = sizes.iter().sum();
+    if total == 0 {
+        return Err(SplitError::TooSmall);
+    }
+
+    let mut lengths: Vec<_> = sizes.iter().map(|size| available * size / total).collect();
+    let used: usize = lengths.iter().sum();
+    if let Some(last) = lengths.last_mut() {
+        *last += available - used;
+    }
+
+    if lengths.contains(&0) {
+        Err(SplitError::TooSmall)
+    } else {
+        Ok(lengths)
+    }
+}
+
+/// The checksum tmux expects in front of a layout
+fn checksum(layout: &str) -> u16 {
+    layout.bytes().fold(0u16, |csum, byte| {
+        let rotated = (csum >> 1) | ((csum & 1) << 15);
+        rotated.wrapping_add(u16::from(byte))
+    })
+}
+
+impl<'de> Deserialize<'de> for SplitChild {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct SplitChildVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for SplitChildVisitor {
+            type Value = SplitChild;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "`pane` or a split")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                if value == "pane" {
+                    Ok(SplitChild::Pane)
+                } else {
+                    Err(E::invalid_value(serde::de::Unexpected::Str(value), &self))
+                }
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<Self::Value, A::Error> {
+                Split::deserialize(serde::de::value::MapAccessDeserializer::new(map))
+                    .map(SplitChild::Split)
+            }
+        }
+
+        deserializer.deserialize_any(SplitChildVisitor)
+    }
+}
+
+impl JsonSchema for SplitChild {
+    fn schema_name() -> Cow<'static, str> {
+        "SplitChild".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Either `pane` or another split",
+            "anyOf": [{ "const": "pane" }, generator.subschema_for::<Split>()],
+        })
+    }
+}
+
+impl Serialize for SplitChild {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::Pane => serializer.serialize_str("pane"),
+            Self::Split(split) => split.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_layout() {
+        let split: Split = serde_yaml::from_str(
+            "direction: horizontal
+sizes: [70, 30]
+children:
+  - pane
+  - direction: vertical
+    children: [pane, pane]",
+        )
+        .unwrap();
+
+        assert_eq!(split.pane_count(), 3);
+        assert_eq!(
+            split.to_layout().unwrap(),
+            "380d,200x50,0,0{139x50,0,0,0,60x50,140,0[60x24,140,0,1,60x25,140,25,2]}"
+        );
+        assert_eq!(layout_pane_count(&split.to_layout().unwrap()), Some(3));
+        assert_eq!(layout_pane_count("even-horizontal"), None);
+        // The checksum of a layout printed by tmux itself
+        assert_eq!(
+            checksum("159x48,0,0{79x48,0,0,79x48,80,0}"),
+            u16::from_str_radix("bb62", 16).unwrap()
+        );
+    }
+}
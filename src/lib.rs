@@ -0,0 +1,32 @@
+//! The logic of moxide, a tmux session manager.
+//!
+//! Configs are read into [`Template`]s, [`Project`]s and [`Directories`],
+//! sessions are built with [`session::start_session`] and saved with [`freeze::freeze_session`].
+//! Everything that can fail returns an [`Error`] instead of ending the process.
+
+pub mod conditions;
+pub mod config_files;
+pub mod directories;
+pub mod environment;
+pub mod error;
+pub mod formats;
+pub mod freeze;
+pub mod helpers;
+pub mod hooks;
+pub mod index;
+pub mod init;
+pub mod layout;
+pub mod projects;
+pub mod readiness;
+pub mod schema;
+pub mod session;
+pub mod sync;
+pub mod templates;
+pub mod tmux;
+pub mod variables;
+pub mod widgets;
+
+pub use directories::Directories;
+pub use error::Error;
+pub use projects::Project;
+pub use templates::{Template, Window};
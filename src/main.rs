@@ -1,17 +1,15 @@
 mod cli;
 mod commands;
-mod directories;
-mod helpers;
-mod init;
-mod projects;
-mod templates;
-mod tmux;
-mod widgets;
+mod exit;
 
 use clap::Parser;
+use std::env;
 
 fn main() {
     let args = cli::Cli::parse();
+    if !args.is_quiet(env::var_os(cli::QUIET_VAR).as_deref()) {
+        moxide::config_files::on_warning(|message| eprintln!("Warning: {message}"));
+    }
 
     match args.cmd {
         cli::Commands::Init => commands::init::init_handler(),
@@ -19,11 +17,18 @@ fn main() {
         cli::Commands::Template { action } => commands::template::template_handler(action),
         cli::Commands::Project { action } => commands::project::project_handler(action),
         cli::Commands::List(args) => commands::list::list_handler(args),
+        cli::Commands::Stop(args) => commands::stop::stop_handler(args),
+        cli::Commands::Restart(args) => commands::restart::restart_handler(args),
+        cli::Commands::Check => commands::check::check_handler(),
+        cli::Commands::Schema { kind } => commands::schema::schema_handler(kind),
         cli::Commands::Freeze {
             name,
             force,
             file_name,
             stdout,
-        } => commands::freeze::freeze_handler(name, force, file_name.as_deref(), stdout),
+            format,
+        } => commands::freeze::freeze_handler(name, force, file_name.as_deref(), stdout, format),
+        cli::Commands::Wait(args) => commands::wait::wait_handler(args),
+        cli::Commands::Type(args) => commands::wait::type_handler(args),
     }
 }
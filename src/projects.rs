@@ -1,57 +1,306 @@
 use crate::{
-    exit,
-    helpers::{get_config_dir, Exit},
-    templates::{find_template, Window},
+    config_files::warn,
+    environment::Environment,
+    formats::scalar_map,
+    helpers::get_config_dir,
+    index::{Index, IndexEntry, Indexed},
+    schema::scalars_schema,
+    templates::{compose_templates, Options, Template, Window},
+    variables::Variables,
     widgets::table::Table,
+    Error,
 };
+use schemars::{JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{borrow::Cow, collections::BTreeMap, fmt, path::PathBuf};
+use thiserror::Error;
 
 #[derive(Serialize, Debug, PartialEq, Eq)]
 pub struct Project {
     pub name: String,
     pub root_dir: PathBuf,
+    #[serde(default, skip_serializing_if = "Variables::is_empty")]
+    pub vars: Variables,
+    #[serde(default, skip_serializing_if = "Environment::is_empty")]
+    pub env: Environment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_create: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_attach: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_stop: Vec<String>,
+    #[serde(default, skip_serializing_if = "Options::is_empty")]
+    pub options: Options,
     #[serde(flatten)]
     pub setup: ProjectSetup,
 }
 
+impl Project {
+    /// Merges the project into the template it uses, so it can be started like a template.
+    /// Settings of the project take precedence over the ones of the template.
+    pub fn into_template(self) -> Result<Template, Error> {
+        let mut template = match self.setup {
+            ProjectSetup::Template {
+                templates,
+                overrides,
+            } => overrides.apply(compose_templates(&templates)?)?.0,
+            ProjectSetup::Windows { windows } => Template {
+                name: self.name.clone(),
+                hidden: None,
+                extends: None,
+                exclude_windows: Vec::new(),
+                vars: BTreeMap::new(),
+                env: Environment::new(),
+                env_file: None,
+                on_create: Vec::new(),
+                on_attach: Vec::new(),
+                on_stop: Vec::new(),
+                options: Options::new(),
+                windows,
+            },
+        };
+
+        template.name = self.name;
+        template
+            .vars
+            .extend(self.vars.into_iter().map(|(key, value)| (key, Some(value))));
+        template.env.extend(self.env);
+        template.env_file = self.env_file.or(template.env_file);
+        template.on_create.extend(self.on_create);
+        template.on_attach.extend(self.on_attach);
+        template.on_stop.extend(self.on_stop);
+        template.options.extend(self.options);
+
+        Ok(template)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum ProjectSetup {
-    Template(String),
-    Windows { windows: Vec<Window> },
+    Template {
+        templates: Vec<String>,
+        #[serde(flatten)]
+        overrides: WindowOverrides,
+    },
+    Windows {
+        windows: Vec<Window>,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum ProjectError {
+    #[error("Project {name} could not be found")]
+    NotFound { name: String },
+    #[error("The window {window} can't be overridden as the template doesn't have it")]
+    UnknownOverride { window: String },
+    #[error("The extra window {window} has the name of a template window, override it instead")]
+    ExtraWindowCollision { window: String },
+}
+
+/// Changes a project makes to the windows of its template
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct WindowOverrides {
+    /// Windows that are appended
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_windows: Vec<Window>,
+    /// Windows that replace the window of the template with the same name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub override_windows: Vec<Window>,
+    /// Names of windows of the template that are dropped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_windows: Vec<String>,
+}
+
+impl WindowOverrides {
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Layers the overrides on top of a template like a template that extends it,
+    /// every window of the result is paired with where it is defined
+    pub fn apply(self, template: Template) -> Result<(Template, Vec<WindowSource>), ProjectError> {
+        let template_has = |name: &Option<String>| {
+            template
+                .windows
+                .iter()
+                .any(|window| name.is_some() && &window.name == name)
+        };
+        if let Some(unmatched) = self
+            .override_windows
+            .iter()
+            .find(|w| !template_has(&w.name))
+        {
+            return Err(ProjectError::UnknownOverride {
+                window: unmatched
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| "without a name".to_string()),
+            });
+        }
+        if let Some(extra) = self.extra_windows.iter().find(|w| template_has(&w.name)) {
+            return Err(ProjectError::ExtraWindowCollision {
+                window: extra.name.clone().unwrap_or_default(),
+            });
+        }
+
+        let overridden: Vec<_> = self
+            .override_windows
+            .iter()
+            .map(|w| w.name.clone())
+            .collect();
+        let extra_count = self.extra_windows.len();
+        let layer = Template {
+            name: template.name.clone(),
+            exclude_windows: self.exclude_windows,
+            windows: [self.override_windows, self.extra_windows].concat(),
+            ..Template::default()
+        };
+        let merged = layer.inherit(template);
+
+        // Extra windows don't replace any window of the template, so they are appended last
+        let first_extra = merged.windows.len() - extra_count;
+        let sources = merged
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(idx, window)| {
+                if idx >= first_extra {
+                    WindowSource::Project
+                } else if overridden.contains(&window.name) {
+                    WindowSource::Override
+                } else {
+                    WindowSource::Template
+                }
+            })
+            .collect();
+        Ok((merged, sources))
+    }
 }
 
-impl From<ProjectSetup> for Vec<Window> {
-    fn from(val: ProjectSetup) -> Self {
-        match val {
-            ProjectSetup::Template(template_name) => {
-                let template = find_template(&template_name)
-                    .unwrap_or_else(|| exit!(1, "Template {} could not be found", template_name));
+/// Where a window of a project is defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSource {
+    Template,
+    Override,
+    Project,
+}
+
+impl fmt::Display for WindowSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Template => write!(f, "template"),
+            Self::Override => write!(f, "override"),
+            Self::Project => write!(f, "project"),
+        }
+    }
+}
 
-                template.windows
+impl ProjectSetup {
+    /// All windows of the project together with where they are defined
+    pub fn sourced_windows(self) -> Result<Vec<(WindowSource, Window)>, Error> {
+        match self {
+            Self::Template {
+                templates,
+                overrides,
+            } => {
+                let (template, sources) = overrides.apply(compose_templates(&templates)?)?;
+                Ok(sources.into_iter().zip(template.windows).collect())
             }
-            ProjectSetup::Windows { windows } => windows,
+            Self::Windows { windows } => Ok(windows
+                .into_iter()
+                .map(|window| (WindowSource::Project, window))
+                .collect()),
         }
     }
 }
 
-impl From<ProjectSetup> for Table<String, String> {
-    fn from(value: ProjectSetup) -> Self {
+impl TryFrom<ProjectSetup> for Vec<Window> {
+    type Error = Error;
+
+    fn try_from(val: ProjectSetup) -> Result<Self, Self::Error> {
+        Ok(val
+            .sourced_windows()?
+            .into_iter()
+            .map(|(_, window)| window)
+            .collect())
+    }
+}
+
+impl TryFrom<ProjectSetup> for Table<String, String> {
+    type Error = Error;
+
+    fn try_from(value: ProjectSetup) -> Result<Self, Self::Error> {
         let template_name = match &value {
-            ProjectSetup::Template(template_name) => Some(template_name.clone()),
+            ProjectSetup::Template { templates, .. } => Some(templates.join(", ")),
             ProjectSetup::Windows { .. } => None,
         };
-        let windows: Vec<Window> = value.into();
-        let windows: Vec<&Window> = windows.iter().collect();
+        let uses_template = template_name.is_some();
 
         let mut rows = Self::new(vec![(
             "Template".to_string(),
             template_name.unwrap_or_else(|| "None".to_string()),
         )]);
-        rows.extend_table(Self::from_iter(windows));
+        for (source, window) in value.sourced_windows()? {
+            let mut table = Self::from(&window);
+            if uses_template {
+                for (_, description) in &mut table.rows {
+                    description.push_str(&format!(" [{source}]"));
+                }
+            }
+            rows.extend_table(table);
+        }
 
-        rows
+        Ok(rows)
+    }
+}
+
+/// A project as it is written, either `template`, `templates` or `windows` is required
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "Project")]
+#[schemars(extend("anyOf" = [
+    { "required": ["template"] },
+    { "required": ["templates"] },
+    { "required": ["windows"] },
+]))]
+struct RawProject {
+    name: String,
+    root_dir: PathBuf,
+    /// The template the session is created from
+    template: Option<String>,
+    /// Templates whose windows are concatenated
+    templates: Option<Vec<String>>,
+    /// The windows of the session if no template is used
+    windows: Option<Vec<Window>>,
+    #[serde(flatten)]
+    overrides: WindowOverrides,
+    #[serde(default, deserialize_with = "scalar_map")]
+    #[schemars(schema_with = "scalars_schema")]
+    vars: Variables,
+    #[serde(default, deserialize_with = "scalar_map")]
+    #[schemars(schema_with = "scalars_schema")]
+    env: Environment,
+    env_file: Option<PathBuf>,
+    #[serde(default)]
+    on_create: Vec<String>,
+    #[serde(default)]
+    on_attach: Vec<String>,
+    #[serde(default)]
+    on_stop: Vec<String>,
+    #[serde(default, deserialize_with = "scalar_map")]
+    #[schemars(schema_with = "scalars_schema")]
+    options: Options,
+}
+
+impl JsonSchema for Project {
+    fn schema_name() -> Cow<'static, str> {
+        RawProject::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        RawProject::json_schema(generator)
     }
 }
 
@@ -60,18 +309,30 @@ impl<'de> Deserialize<'de> for Project {
     where
         D: serde::Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        struct RawProject {
-            name: String,
-            root_dir: PathBuf,
-            template: Option<String>,
-            windows: Option<Vec<Window>>,
-        }
-
         let raw = RawProject::deserialize(deserializer)?;
 
-        let setup = if let Some(template) = raw.template {
-            ProjectSetup::Template(template)
+        let templates = match (raw.template, raw.templates) {
+            (Some(_), Some(_)) => {
+                return Err(serde::de::Error::custom(
+                    "Expected either template or templates",
+                ))
+            }
+            (_, Some(templates)) if templates.is_empty() => {
+                return Err(serde::de::Error::custom("templates can't be empty"))
+            }
+            (Some(template), None) => Some(vec![template]),
+            (None, templates) => templates,
+        };
+
+        let setup = if let Some(templates) = templates {
+            ProjectSetup::Template {
+                templates,
+                overrides: raw.overrides,
+            }
+        } else if !raw.overrides.is_empty() {
+            return Err(serde::de::Error::custom(
+                "extra_windows, override_windows and exclude_windows require a template",
+            ));
         } else if let Some(windows) = raw.windows {
             ProjectSetup::Windows { windows }
         } else {
@@ -83,63 +344,66 @@ impl<'de> Deserialize<'de> for Project {
         Ok(Self {
             name: raw.name,
             root_dir: raw.root_dir,
+            vars: raw.vars,
+            env: raw.env,
+            env_file: raw.env_file,
+            on_create: raw.on_create,
+            on_attach: raw.on_attach,
+            on_stop: raw.on_stop,
+            options: raw.options,
             setup,
         })
     }
 }
 
-pub fn find_project(name: &str) -> Option<Project> {
-    let projects_dir = get_config_dir().join("projects/");
-    let file_path = projects_dir.join(format!("{name}.yaml"));
-    let is_valid_path = file_path.exists() && file_path.is_file();
-    let matching_path = is_valid_path.then_some(file_path);
-
-    let matching_project = matching_path.and_then(|path| {
-        let content = fs::read_to_string(&path).ok()?;
-        let project = serde_yaml::from_str::<Project>(&content).ok()?;
-        (project.name == name).then_some(project)
-    });
-
-    if matching_project.is_some() {
-        return matching_project;
+impl Indexed for Project {
+    fn name(&self) -> &str {
+        &self.name
     }
+}
 
-    fs::read_dir(&projects_dir)
-        .exit(1, "Can't read template config")
-        .find_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if !path.is_file() {
-                return None;
-            }
-
-            let content = fs::read_to_string(&path).ok()?;
-            let project = serde_yaml::from_str::<Project>(&content).ok()?;
-            (project.name == name).then_some(project)
-        })
+fn project_index() -> Result<Index, Error> {
+    Index::of::<Project>(&get_config_dir()?.join("projects/"))
 }
 
-pub fn parse_project_config() -> Vec<Project> {
-    let projects_dir = get_config_dir().join("projects/");
-    let projects_content = fs::read_dir(&projects_dir).exit(1, "Can't read template config");
+pub fn find_project(name: &str) -> Result<Project, Error> {
+    let index = project_index()?;
+    let Some((qualified, mut project)) = index.load::<Project>(name)? else {
+        // The project might be in a file that can't be parsed
+        index.warn_broken();
+        return Err(ProjectError::NotFound {
+            name: name.to_string(),
+        }
+        .into());
+    };
+    project.name = qualified;
+    Ok(project)
+}
 
-    projects_content
+pub fn parse_project_config() -> Result<Vec<Project>, Error> {
+    let index = project_index()?;
+    index.warn_broken();
+    Ok(index
+        .configs()
         .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if !path.is_file() {
-                return None;
-            }
-
-            let content = fs::read_to_string(&path).ok()?;
-            serde_yaml::from_str::<Project>(&content).ok()
+            let mut project = entry.load::<Project>().map_err(warn).ok()?;
+            project.name = entry.name.clone()?;
+            Some(project)
         })
-        .collect()
+        .collect())
+}
+
+/// All projects, read from the index without parsing every file
+pub fn indexed_projects() -> Result<Vec<IndexEntry>, Error> {
+    let index = project_index()?;
+    index.warn_broken();
+    Ok(index.configs().cloned().collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::templates::Pane;
 
     #[test]
     fn test_parser() {
@@ -162,17 +426,32 @@ windows:
             Project {
                 name: "OsmApp".to_string(),
                 root_dir: PathBuf::from("~/GitHub/osmapp"),
+                vars: Variables::new(),
+                env: Environment::new(),
+                env_file: None,
+                on_create: Vec::new(),
+                on_attach: Vec::new(),
+                on_stop: Vec::new(),
+                options: Options::new(),
                 setup: ProjectSetup::Windows {
                     windows: vec![
                         Window {
                             name: Some(" Neovim".to_string()),
-                            panes: vec!["nvim".to_string()],
+                            panes: vec![Pane::from("nvim".to_string())],
                             layout: None,
+                            dir: None,
+                            options: Options::new(),
+                            split: None,
+                            when: None,
                         },
                         Window {
                             name: Some("Server".to_string()),
-                            panes: vec!["yarn run dev".to_string()],
+                            panes: vec![Pane::from("yarn run dev".to_string())],
                             layout: None,
+                            dir: None,
+                            options: Options::new(),
+                            split: None,
+                            when: None,
                         }
                     ]
                 }
@@ -192,8 +471,73 @@ template: Svelte",
             Project {
                 name: "Dlool".to_string(),
                 root_dir: PathBuf::from("~/SoftwareDevelopment/web/Dlool/dlool_frontend_v2/"),
-                setup: ProjectSetup::Template("Svelte".to_string())
+                vars: Variables::new(),
+                env: Environment::new(),
+                env_file: None,
+                on_create: Vec::new(),
+                on_attach: Vec::new(),
+                on_stop: Vec::new(),
+                options: Options::new(),
+                setup: ProjectSetup::Template {
+                    templates: vec!["Svelte".to_string()],
+                    overrides: WindowOverrides::default(),
+                }
             }
         );
     }
+
+    #[test]
+    fn test_overrides() {
+        let window = |name: &str, cmd: &str| Window {
+            name: Some(name.to_string()),
+            panes: vec![Pane::from(cmd.to_string())],
+            layout: None,
+            dir: None,
+            options: Options::new(),
+            split: None,
+            when: None,
+        };
+
+        let overrides = WindowOverrides {
+            extra_windows: vec![window("Docs", "mdbook serve")],
+            override_windows: vec![window("Cargo", "cargo watch")],
+            exclude_windows: vec!["Notes".to_string()],
+        };
+        let template = Template {
+            name: "Rust".to_string(),
+            windows: vec![
+                window("Neovim", "nvim"),
+                window("Cargo", "cargo build"),
+                window("Notes", "nvim notes.md"),
+            ],
+            ..Template::default()
+        };
+        let (merged, sources) = overrides.apply(template.clone()).unwrap();
+
+        assert_eq!(
+            merged.windows,
+            vec![
+                window("Neovim", "nvim"),
+                window("Cargo", "cargo watch"),
+                window("Docs", "mdbook serve"),
+            ]
+        );
+        assert_eq!(
+            sources,
+            vec![
+                WindowSource::Template,
+                WindowSource::Override,
+                WindowSource::Project,
+            ]
+        );
+
+        let overrides = WindowOverrides {
+            extra_windows: vec![window("Notes", "nvim todo.md")],
+            ..WindowOverrides::default()
+        };
+        assert!(matches!(
+            overrides.apply(template),
+            Err(ProjectError::ExtraWindowCollision { window }) if window == "Notes"
+        ));
+    }
 }
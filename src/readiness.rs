@@ -0,0 +1,440 @@
+use crate::helpers::{escape_format, shell_quote};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tmux_interface::{CapturePane, DisplayMessage, RunShell, SendKeys, Tmux, TmuxCommand};
+
+const DEFAULT_TIMEOUT: u64 = 30;
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Polls in a row that have to show the same prompt before a shell counts as idle
+const IDLE_POLLS: usize = 3;
+const DEFAULT_PROMPT_TIMEOUT: u64 = 10;
+/// Seconds moxide waits for the shell of a pane to become idle before typing the command
+pub const PROMPT_TIMEOUT_VAR: &str = "MOXIDE_PROMPT_TIMEOUT";
+const SHELLS: [&str; 8] = ["sh", "bash", "zsh", "fish", "dash", "ksh", "nu", "xonsh"];
+
+/// Conditions a pane waits for before its command is sent, all given checks have to pass
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WaitFor {
+    /// A TCP port on localhost that has to accept connections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// A file that has to exist, relative to the directory of the pane
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// A regex the output of a previous pane has to match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// The index of the pane whose output is checked, defaults to the previous pane
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pane: Option<usize>,
+    /// Seconds until the wait is given up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Error)]
+pub enum WaitError {
+    #[error("`{pattern}` is not a valid regex: {source}")]
+    InvalidRegex {
+        pattern: String,
+        source: regex::Error,
+    },
+    #[error("Pane {pane} of window {window} can only wait for the output of a previous pane")]
+    NotPrevious { window: String, pane: usize },
+    #[error("Timed out after {seconds}s waiting for {pending}")]
+    Timeout { seconds: u64, pending: String },
+    #[error("Pane {pane} of window {window} waits for the output of pane {watched}, but its when condition isn't met")]
+    SkippedPane {
+        window: String,
+        pane: usize,
+        watched: usize,
+    },
+    #[error("The panes of session {session} could not be listed")]
+    ListPanes { session: String },
+}
+
+impl WaitFor {
+    pub fn timeout(&self) -> u64 {
+        self.timeout.unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// The index of the pane whose output is checked by the pane at `own_index`
+    pub fn output_pane(&self, own_index: usize) -> Option<usize> {
+        self.output.as_ref()?;
+        self.pane.or(own_index.checked_sub(1))
+    }
+
+    /// Checks the regex and that only previous panes are referenced
+    pub fn validate(&self, window: &str, own_index: usize) -> Result<(), WaitError> {
+        if let Some(pattern) = &self.output {
+            compile(pattern)?;
+            if self
+                .output_pane(own_index)
+                .is_none_or(|pane| pane >= own_index)
+            {
+                return Err(WaitError::NotPrevious {
+                    window: window.to_string(),
+                    pane: own_index,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Blocks until all checks pass, `pane_output` returns the contents of the watched pane
+    pub fn wait<F>(&self, dir: Option<&Path>, pane_output: F) -> Result<(), WaitError>
+    where
+        F: Fn() -> Option<String>,
+    {
+        self.wait_from(Instant::now(), dir, pane_output)
+    }
+
+    /// Like `wait`, but the timeout counts from `start` instead of from now
+    fn wait_from<F>(
+        &self,
+        start: Instant,
+        dir: Option<&Path>,
+        pane_output: F,
+    ) -> Result<(), WaitError>
+    where
+        F: Fn() -> Option<String>,
+    {
+        let regex = self.output.as_deref().map(compile).transpose()?;
+        let deadline = start + Duration::from_secs(self.timeout());
+
+        loop {
+            let Some(pending) = self.pending(dir, regex.as_ref(), &pane_output) else {
+                return Ok(());
+            };
+            if Instant::now() >= deadline {
+                return Err(WaitError::Timeout {
+                    seconds: self.timeout(),
+                    pending,
+                });
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Describes the first check that doesn't pass yet
+    fn pending<F>(
+        &self,
+        dir: Option<&Path>,
+        regex: Option<&Regex>,
+        pane_output: F,
+    ) -> Option<String>
+    where
+        F: Fn() -> Option<String>,
+    {
+        if let Some(port) = self.port {
+            if TcpStream::connect(("localhost", port)).is_err() {
+                return Some(format!("port {port}"));
+            }
+        }
+
+        if let Some(file) = &self.file {
+            let path = dir.map_or_else(|| file.clone(), |dir| dir.join(file));
+            if !path.exists() {
+                return Some(file.display().to_string());
+            }
+        }
+
+        if let Some(regex) = regex {
+            if !pane_output().is_some_and(|output| regex.is_match(&output)) {
+                return Some(format!("output matching `{regex}`"));
+            }
+        }
+
+        None
+    }
+
+    /// The shell command that blocks a pane until the checks pass,
+    /// `output_pane` is the position of the watched pane among the created panes of the window
+    pub fn shell_command(&self, output_pane: Option<usize>) -> String {
+        let mut args = vec![
+            moxide_exe(),
+            "wait".to_string(),
+            format!("--timeout {}", self.timeout()),
+        ];
+        if let Some(port) = self.port {
+            args.push(format!("--port {port}"));
+        }
+        if let Some(file) = &self.file {
+            args.push(format!("--file {}", shell_quote(&file.to_string_lossy())));
+        }
+        if let (Some(output), Some(pane)) = (&self.output, output_pane) {
+            args.push(format!("--pane {pane} --output {}", shell_quote(output)));
+        }
+
+        args.join(" ")
+    }
+}
+
+/// The moxide binary that is running, quoted for the shell
+fn moxide_exe() -> String {
+    let exe = env::current_exe().map_or_else(
+        |_| "moxide".to_string(),
+        |exe| exe.to_string_lossy().into_owned(),
+    );
+    shell_quote(&exe)
+}
+
+pub fn prompt_timeout() -> u64 {
+    env::var(PROMPT_TIMEOUT_VAR)
+        .ok()
+        .and_then(|timeout| timeout.parse().ok())
+        .unwrap_or(DEFAULT_PROMPT_TIMEOUT)
+}
+
+/// A tmux command that types `command_line` into the pane it targets once its shell is idle,
+/// it has to run right after the pane is created
+pub fn type_when_idle(pane: &str, command_line: &str) -> TmuxCommand<'static> {
+    let script = format!(
+        "{} type --pane '#{{pane_id}}' --timeout {} -- {}",
+        escape_format(&moxide_exe()),
+        prompt_timeout(),
+        escape_format(&shell_quote(command_line)),
+    );
+    RunShell::new()
+        .background()
+        .target_pane(pane.to_string())
+        .shell_command(script)
+        .into()
+}
+
+/// Blocks until a shell runs in the pane and shows a prompt that doesn't change for a few polls,
+/// returns false if that doesn't happen within `timeout` seconds
+pub fn wait_for_prompt(pane: &str, timeout: u64) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let mut previous = None;
+    let mut stable = 0;
+
+    loop {
+        let screen = is_shell_running(pane)
+            .then(|| pane_screen(pane))
+            .flatten()
+            .filter(PaneScreen::shows_prompt);
+        stable = match &screen {
+            Some(_) if screen == previous => stable + 1,
+            Some(_) => 1,
+            None => 0,
+        };
+        if stable >= IDLE_POLLS {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+
+        previous = screen;
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The visible contents of a pane and the position of its cursor
+#[derive(Debug, PartialEq, Eq)]
+struct PaneScreen {
+    contents: String,
+    cursor_x: usize,
+    cursor_y: usize,
+}
+
+impl PaneScreen {
+    /// A prompt is the last line with text and the cursor waits behind it.
+    /// Output of an rc file that stalls leaves the cursor at the start of an empty line.
+    fn shows_prompt(&self) -> bool {
+        let mut lines = self.contents.lines().skip(self.cursor_y);
+        let cursor_line = lines.next().unwrap_or_default();
+        self.cursor_x > 0
+            && !cursor_line.trim().is_empty()
+            && lines.all(|line| line.trim().is_empty())
+    }
+}
+
+/// Reads the cursor and the screen in one call, so both belong to the same moment
+fn pane_screen(pane: &str) -> Option<PaneScreen> {
+    let cursor_cmd = DisplayMessage::new()
+        .print()
+        .target_pane(pane)
+        .message("#{cursor_x} #{cursor_y}");
+    let capture_cmd = CapturePane::new().stdout().target_pane(pane);
+    let output = Tmux::new()
+        .add_command(cursor_cmd)
+        .add_command(capture_cmd)
+        .output()
+        .ok()?;
+    if !output.success() {
+        return None;
+    }
+
+    let output = String::from_utf8(output.stdout()).ok()?;
+    let (cursor, contents) = output.split_once('\n')?;
+    let (cursor_x, cursor_y) = cursor.split_once(' ')?;
+    Some(PaneScreen {
+        contents: contents.to_string(),
+        cursor_x: cursor_x.parse().ok()?,
+        cursor_y: cursor_y.parse().ok()?,
+    })
+}
+
+/// Whether the pane runs a shell and nothing else
+pub fn is_shell_running(pane: &str) -> bool {
+    let display_cmd = DisplayMessage::new()
+        .print()
+        .target_pane(pane)
+        .message("#{pane_current_command}");
+    let Some(command) = list_output(display_cmd) else {
+        return false;
+    };
+    let command = command.trim().trim_start_matches('-');
+
+    let user_shell = env::var("SHELL").ok();
+    let user_shell = user_shell
+        .as_deref()
+        .and_then(|shell| Path::new(shell).file_name())
+        .and_then(|name| name.to_str());
+    SHELLS.contains(&command) || user_shell == Some(command)
+}
+
+/// Types the command line into the pane and presses enter
+pub fn type_command(pane: &str, command_line: &str) -> bool {
+    let text = SendKeys::new()
+        .disable_lookup()
+        .target_pane(pane)
+        .key(command_line);
+    let enter = SendKeys::new().target_pane(pane).key("Enter");
+
+    Tmux::new()
+        .add_command(text)
+        .add_command(enter)
+        .output()
+        .is_ok_and(|output| output.success())
+}
+
+fn compile(pattern: &str) -> Result<Regex, WaitError> {
+    Regex::new(pattern).map_err(|source| WaitError::InvalidRegex {
+        pattern: pattern.to_string(),
+        source,
+    })
+}
+
+/// A pane of a session that waits for something
+#[derive(Debug)]
+pub struct PaneWait<'a> {
+    pub wait: &'a WaitFor,
+    pub dir: Option<PathBuf>,
+    /// The position of the watched pane among all panes of the session
+    pub output_pane: Option<usize>,
+}
+
+/// Blocks until all panes of the session are ready,
+/// the timeouts of all panes count from the same start as the panes wait at the same time
+pub fn wait_for_session(session: &str, waits: &[PaneWait]) -> Result<(), WaitError> {
+    if waits.is_empty() {
+        return Ok(());
+    }
+
+    let list_cmd = TmuxCommand::list_panes()
+        .session()
+        .target(session)
+        .format("#{pane_id}");
+    let pane_ids = list_output(list_cmd).ok_or_else(|| WaitError::ListPanes {
+        session: session.to_string(),
+    })?;
+    let pane_ids: Vec<_> = pane_ids.lines().collect();
+
+    let start = Instant::now();
+    for pane_wait in waits {
+        let target = pane_wait
+            .output_pane
+            .and_then(|pane| pane_ids.get(pane).copied());
+        pane_wait
+            .wait
+            .wait_from(start, pane_wait.dir.as_deref(), || capture_pane(target?))?;
+    }
+
+    Ok(())
+}
+
+/// The contents of the pane at `position` in the window of the pane moxide runs in
+pub fn window_pane_output(position: usize) -> Option<String> {
+    let own_pane = env::var("TMUX_PANE").ok()?;
+    let list_cmd = TmuxCommand::list_panes()
+        .target(own_pane)
+        .format("#{pane_id}");
+    let pane_ids = list_output(list_cmd)?;
+    let target = pane_ids.lines().nth(position)?;
+
+    capture_pane(target)
+}
+
+fn capture_pane(target: &str) -> Option<String> {
+    let capture_cmd = CapturePane::new()
+        .stdout()
+        .start_line("-")
+        .target_pane(target);
+    list_output(capture_cmd)
+}
+
+pub(crate) fn list_output<'a, C: Into<TmuxCommand<'a>>>(cmd: C) -> Option<String> {
+    let output = Tmux::with_command(cmd).output().ok()?;
+    output
+        .success()
+        .then(|| String::from_utf8(output.stdout()).ok())
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let wait: WaitFor = serde_yaml::from_str("output: 'ready in \\d+ms'").unwrap();
+        assert_eq!(wait.output_pane(2), Some(1));
+        assert!(wait.validate("Dev", 1).is_ok());
+        assert!(matches!(
+            wait.validate("Dev", 0),
+            Err(WaitError::NotPrevious { pane: 0, .. })
+        ));
+
+        let wait: WaitFor = serde_yaml::from_str("{output: '(', pane: 0}").unwrap();
+        assert!(matches!(
+            wait.validate("Dev", 1),
+            Err(WaitError::InvalidRegex { .. })
+        ));
+    }
+
+    #[test]
+    fn test_type_when_idle() {
+        let args = type_when_idle("dev:", "echo #1").to_vec();
+        assert_eq!(args[..4], ["run", "-b", "-t", "dev:"]);
+        assert!(args[4].ends_with(" -- 'echo ##1'"));
+    }
+
+    #[test]
+    fn test_shows_prompt() {
+        let screen = |contents: &str, cursor_x, cursor_y| PaneScreen {
+            contents: contents.to_string(),
+            cursor_x,
+            cursor_y,
+        };
+
+        assert!(screen("warning: slow rc\nuser@host:~$\n\n", 13, 1).shows_prompt());
+        // The rc file printed a warning and still runs
+        assert!(!screen("warning: slow rc\n\n\n", 0, 1).shows_prompt());
+        assert!(!screen("\n\n", 0, 0).shows_prompt());
+        assert!(!screen("$\nloading plugins\n", 2, 0).shows_prompt());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{directories::Directories, projects::Project, templates::Template};
+use clap::ValueEnum;
+use schemars::{json_schema, schema_for, Schema, SchemaGenerator};
+
+/// The layouts tmux has built in
+const LAYOUT_PRESETS: [&str; 7] = [
+    "even-horizontal",
+    "even-vertical",
+    "main-horizontal",
+    "main-horizontal-mirrored",
+    "main-vertical",
+    "main-vertical-mirrored",
+    "tiled",
+];
+
+/// The config files a schema can be printed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SchemaKind {
+    Project,
+    Template,
+    Directories,
+}
+
+impl SchemaKind {
+    pub fn schema(self) -> Schema {
+        match self {
+            Self::Project => schema_for!(Project),
+            Self::Template => schema_for!(Template),
+            Self::Directories => schema_for!(Directories),
+        }
+    }
+}
+
+/// A preset or a custom layout string, both are offered for completion
+pub fn layout_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "anyOf": [
+            { "enum": LAYOUT_PRESETS },
+            { "type": "string" },
+            { "type": "null" },
+        ],
+    })
+}
+
+/// A map of strings, booleans and numbers are read as strings in every format
+pub fn scalars_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "object",
+        "additionalProperties": { "type": ["string", "boolean", "number"] },
+    })
+}
+
+/// Like `scalars_schema`, but values can be left empty
+pub fn optional_scalars_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "object",
+        "additionalProperties": { "type": ["string", "boolean", "number", "null"] },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemas() {
+        let project = SchemaKind::Project.schema();
+        assert_eq!(
+            project.get("required"),
+            Some(&serde_json::json!(["name", "root_dir"]))
+        );
+
+        let template = serde_json::to_value(SchemaKind::Template.schema()).unwrap();
+        let layout = &template["$defs"]["Window"]["properties"]["layout"];
+        assert_eq!(layout["anyOf"][0]["enum"][0], "even-horizontal");
+        assert_eq!(
+            template["$defs"]["Window"]["required"],
+            serde_json::json!(["panes"])
+        );
+    }
+}
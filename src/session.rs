@@ -0,0 +1,562 @@
+use crate::{
+    config_files::warn,
+    helpers::{absolute_path, apply_if_some, runs_in_tmux},
+    hooks,
+    projects::find_project,
+    readiness::{self, is_shell_running, list_output},
+    templates::{apply_windows, find_template, pane_waits, Template, Window},
+    tmux,
+    variables::Variables,
+    Error,
+};
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tmux_interface::{
+    DisplayMessage, KillSession, ListClients, ListSessions, NewSession, RenameSession, SendKeys,
+    SetOption, Tmux, TmuxCommand,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The user options a session is tagged with
+const KIND_OPTION: &str = "@moxide-kind";
+const NAME_OPTION: &str = "@moxide-name";
+const ROOT_OPTION: &str = "@moxide-root";
+
+/// The kind of config a session is started from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SessionKind {
+    Project,
+    Template,
+    Dir,
+}
+
+/// What a session was started from, moxide stores it in user options of the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionTag {
+    pub kind: SessionKind,
+    /// The qualified name of the project or template, or the name of the directory
+    pub name: String,
+    /// The root directory of the session
+    pub root: Option<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("No session called {name} is running")]
+    NotRunning { name: String },
+    #[error("The session {name} wasn't started from a {kind}")]
+    WrongKind { name: String, kind: SessionKind },
+    #[error("The session {name} wasn't started by moxide")]
+    NotManaged { name: String },
+    #[error("The root directory {} could not be found: {source}", path.display())]
+    MissingRoot { path: PathBuf, source: io::Error },
+    #[error("The {kind} {name} isn't running")]
+    NoSession { kind: SessionKind, name: String },
+    #[error("The window {window} could not be synced: {message}")]
+    SyncWindow { window: String, message: String },
+    #[error("The layout of window {window} has {leaves} panes, but the synced window would have {panes}")]
+    SyncPaneCount {
+        window: String,
+        leaves: usize,
+        panes: usize,
+    },
+}
+
+/// How a new session is started
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StartOptions {
+    /// Don't attach to the session
+    pub detached: bool,
+    /// Wait until all panes are ready before attaching
+    pub wait: bool,
+}
+
+/// Creates the session `name` from the template and its resolved windows and tags it.
+/// The `on_create` hooks run first, the root of the tag is the directory of the session.
+pub fn start_session(
+    name: &str,
+    tag: &SessionTag,
+    template: &Template,
+    windows: &[Window],
+    options: StartOptions,
+) -> Result<(), Error> {
+    let root = tag.root.as_deref();
+    let env = template.environment(root)?;
+    let waits = pane_waits(windows, root)?;
+    hooks::run_on_create(&template.on_create, root, &env)?;
+
+    let new_session_cmd = apply_if_some(
+        NewSession::new().detached().session_name(name),
+        root,
+        |cmd, root| cmd.start_directory(root.to_string_lossy().into_owned()),
+    );
+    let session_tmux =
+        Tmux::new().add_command(tmux::with_environment(new_session_cmd.into(), &env));
+    let session_hooks = hooks::tmux_hooks(&template.on_attach, &template.on_stop, name, root);
+
+    let initial_tmux = apply_if_some(
+        session_hooks
+            .into_iter()
+            .chain(tag.tmux_commands(name))
+            .fold(session_tmux, |tmux, cmd| tmux.add_command(cmd)),
+        (!options.detached && !options.wait).then(|| tmux::attach(name)),
+        |tmux, cmd| tmux.add_command(cmd),
+    );
+
+    apply_windows(initial_tmux, name, windows, &template.options, root).output()?;
+
+    if options.wait {
+        readiness::wait_for_session(name, &waits)?;
+        attach_session(name, options.detached)?;
+    }
+
+    Ok(())
+}
+
+/// Attaches to the existing session `name` unless `detached` is set
+pub fn attach_session(name: &str, detached: bool) -> Result<(), Error> {
+    if !detached {
+        Tmux::with_command(tmux::attach(name)).output()?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for SessionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Project => write!(f, "project"),
+            Self::Template => write!(f, "template"),
+            Self::Dir => write!(f, "directory"),
+        }
+    }
+}
+
+impl SessionTag {
+    /// The commands that store the tag in the session `name`
+    fn tmux_commands(&self, name: &str) -> Vec<TmuxCommand<'static>> {
+        let kind = self
+            .kind
+            .to_possible_value()
+            .map(|value| value.get_name().to_string());
+        let root = self
+            .root
+            .as_ref()
+            .map(|root| root.to_string_lossy().into_owned());
+        let options = [
+            (KIND_OPTION, kind),
+            (NAME_OPTION, Some(self.name.clone())),
+            (ROOT_OPTION, root),
+        ];
+
+        options
+            .into_iter()
+            .filter_map(|(option, value)| {
+                let cmd = SetOption::new()
+                    .target_pane(format!("{name}:"))
+                    .option(option)
+                    .value(value?);
+                Some(cmd.into())
+            })
+            .collect()
+    }
+
+    /// Builds the tag from the values of its options, sessions without a tag are `None`
+    fn from_options(kind: Option<&str>, name: Option<&str>, root: Option<&str>) -> Option<Self> {
+        let kind = SessionKind::from_str(kind?, false).ok()?;
+        let name = name.filter(|name| !name.is_empty())?.to_string();
+        let root = root.filter(|root| !root.is_empty()).map(PathBuf::from);
+
+        Some(Self { kind, name, root })
+    }
+}
+
+/// Lists all sessions in `format`, one per line
+fn list_sessions(format: &str) -> Result<Vec<String>, Error> {
+    let output = Tmux::with_command(ListSessions::new().format(format)).output()?;
+    // Without a tmux server there are no sessions
+    if !output.success() {
+        return Ok(Vec::new());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout()).into_owned();
+    Ok(stdout.lines().map(str::to_string).collect())
+}
+
+/// The names of all running sessions
+pub fn running_sessions() -> Result<Vec<String>, Error> {
+    list_sessions("#{session_name}")
+}
+
+/// The value of `format` for every session by session id. Each value is read on
+/// its own, so names and paths can contain any separator.
+fn session_values(format: &str) -> Result<HashMap<String, String>, Error> {
+    let sessions = list_sessions(&format!("#{{session_id}} {format}"))?;
+    Ok(sessions
+        .iter()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(id, value)| (id.to_string(), value.to_string()))
+        .collect())
+}
+
+/// The running sessions that moxide started with their tags
+pub fn managed_sessions() -> Result<Vec<(String, SessionTag)>, Error> {
+    let sessions = list_sessions("#{session_id} #{session_name}")?;
+    let kinds = session_values(&format!("#{{{KIND_OPTION}}}"))?;
+    let names = session_values(&format!("#{{{NAME_OPTION}}}"))?;
+    let roots = session_values(&format!("#{{{ROOT_OPTION}}}"))?;
+
+    Ok(sessions
+        .iter()
+        .filter_map(|line| line.split_once(' '))
+        .filter_map(|(id, session)| {
+            let tag = SessionTag::from_options(
+                kinds.get(id).map(String::as_str),
+                names.get(id).map(String::as_str),
+                roots.get(id).map(String::as_str),
+            )?;
+            Some((session.to_string(), tag))
+        })
+        .collect())
+}
+
+/// A running session that was started with the same tag
+pub fn find_session(tag: &SessionTag) -> Result<Option<String>, Error> {
+    let mut sessions = managed_sessions()?.into_iter();
+    Ok(sessions
+        .find(|(_, session_tag)| session_tag == tag)
+        .map(|(session, _)| session))
+}
+
+/// The sessions `moxide stop` affects: the session `name`, or all managed sessions.
+/// Both can be restricted to a kind.
+pub fn stop_targets(name: Option<&str>, kind: Option<SessionKind>) -> Result<Vec<String>, Error> {
+    let running = match name {
+        Some(_) => running_sessions()?,
+        None => Vec::new(),
+    };
+    select_stop_targets(name, kind, &running, &managed_sessions()?)
+}
+
+/// Picks the targets of `moxide stop` from the `running` and `managed` sessions.
+/// A session given by name has to be managed, other tmux sessions are never stopped.
+fn select_stop_targets(
+    name: Option<&str>,
+    kind: Option<SessionKind>,
+    running: &[String],
+    managed: &[(String, SessionTag)],
+) -> Result<Vec<String>, Error> {
+    let Some(name) = name else {
+        return Ok(managed
+            .iter()
+            .filter(|(_, tag)| kind.is_none_or(|kind| kind == tag.kind))
+            .map(|(session, _)| session.clone())
+            .collect());
+    };
+
+    if !running.iter().any(|session| session == name) {
+        return Err(SessionError::NotRunning {
+            name: name.to_string(),
+        }
+        .into());
+    }
+    let Some((_, tag)) = managed.iter().find(|(session, _)| session == name) else {
+        return Err(SessionError::NotManaged {
+            name: name.to_string(),
+        }
+        .into());
+    };
+    if let Some(kind) = kind.filter(|&kind| kind != tag.kind) {
+        return Err(SessionError::WrongKind {
+            name: name.to_string(),
+            kind,
+        }
+        .into());
+    }
+
+    Ok(vec![name.to_string()])
+}
+
+/// Kills the sessions, a client inside one of them is switched to another session first.
+/// With a `graceful` timeout every pane gets `C-c` and the panes have that long to finish.
+pub fn stop_sessions(names: &[String], graceful: Option<Duration>) -> Result<(), Error> {
+    leave_sessions(names)?;
+
+    if let Some(timeout) = graceful {
+        interrupt_panes(names, timeout)?;
+    }
+    let managed = managed_sessions()?;
+    for name in names {
+        if let Some((_, tag)) = managed.iter().find(|(session, _)| session == name) {
+            run_on_stop(name, tag);
+        }
+        Tmux::with_command(KillSession::new().target_session(name)).output()?;
+    }
+
+    Ok(())
+}
+
+/// Runs the `on_stop` hooks of the session before it is killed instead of leaving them to
+/// the tmux hook, which never runs if the server exits together with its last session
+fn run_on_stop(session: &str, tag: &SessionTag) {
+    let result = session_config(session, tag.clone()).and_then(|(_, template)| {
+        Tmux::with_command(hooks::remove_on_stop(session)).output()?;
+        hooks::run_on_stop(&template.on_stop, tag.root.as_deref())?;
+        Ok(())
+    });
+    if let Err(err) = result {
+        warn(format!("The on_stop hooks of {session} failed: {err}"));
+    }
+}
+
+/// The session of the client moxide runs in
+pub fn current_session() -> Option<String> {
+    if !runs_in_tmux() {
+        return None;
+    }
+    let display_cmd = DisplayMessage::new().print().message("#{client_session}");
+    list_output(display_cmd).map(|session| session.trim().to_string())
+}
+
+/// Switches the client to a session that isn't stopped, if it is in one of `names`
+fn leave_sessions(names: &[String]) -> Result<(), Error> {
+    let Some(current) = current_session().filter(|current| names.contains(current)) else {
+        return Ok(());
+    };
+
+    let other = running_sessions()?
+        .into_iter()
+        .find(|session| session != &current && !names.contains(session));
+    // Without another session the client detaches once its session is killed
+    if let Some(other) = other {
+        Tmux::with_command(TmuxCommand::switch_client().target_session(other)).output()?;
+    }
+
+    Ok(())
+}
+
+/// Sends `C-c` to all panes and waits until each of them runs only a shell
+fn interrupt_panes(names: &[String], timeout: Duration) -> Result<(), Error> {
+    let panes = || -> Vec<String> { names.iter().flat_map(|name| session_panes(name)).collect() };
+
+    let interrupts = panes()
+        .into_iter()
+        .map(|pane| SendKeys::new().target_pane(pane).key("C-c"));
+    let tmux = interrupts.fold(Tmux::new(), |tmux, cmd| tmux.add_command(cmd));
+    tmux.output()?;
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline && !panes().iter().all(|pane| is_shell_running(pane)) {
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the running session `name` from its current config.
+/// The new session takes over the name, the focused window and pane and the attached clients,
+/// the old session is only killed at the end, so this also works from inside of it.
+pub fn restart_session(name: &str, vars: &Variables) -> Result<(), Error> {
+    let tag = managed_sessions()?
+        .into_iter()
+        .find(|(session, _)| session == name)
+        .map(|(_, tag)| tag);
+    let Some(tag) = tag else {
+        return Err(if tmux::session_exists(name)? {
+            SessionError::NotManaged {
+                name: name.to_string(),
+            }
+        } else {
+            SessionError::NotRunning {
+                name: name.to_string(),
+            }
+        }
+        .into());
+    };
+
+    let (tag, template) = session_config(name, tag)?;
+    let windows = template.resolve_windows(vars)?;
+    let focus = session_focus(name);
+    let clients = list_output(
+        ListClients::new()
+            .target_session(name)
+            .format("#{client_name}"),
+    )
+    .unwrap_or_default();
+
+    // Frees the name for the new session
+    let old_name = tmux::get_unused_name(name);
+    Tmux::with_command(
+        RenameSession::new()
+            .target_session(name)
+            .new_name(&old_name),
+    )
+    .output()?;
+
+    let options = StartOptions {
+        detached: true,
+        ..StartOptions::default()
+    };
+    if let Err(err) = start_session(name, &tag, &template, &windows, options) {
+        let rename_cmd = RenameSession::new()
+            .target_session(&old_name)
+            .new_name(name);
+        Tmux::with_command(rename_cmd).output()?;
+        return Err(err);
+    }
+
+    restore_focus(name, focus.as_deref())?;
+
+    let switches = clients.lines().map(|client| {
+        TmuxCommand::switch_client()
+            .target_client(client)
+            .target_session(name)
+    });
+    let tmux = switches.fold(Tmux::new(), |tmux, cmd| tmux.add_command(cmd));
+    tmux.add_command(KillSession::new().target_session(old_name))
+        .output()?;
+
+    Ok(())
+}
+
+/// The active window and pane of the session as `window.pane`
+pub(crate) fn session_focus(name: &str) -> Option<String> {
+    let display_cmd = DisplayMessage::new()
+        .print()
+        .target_pane(name)
+        .message("#{window_index}.#{pane_index}");
+    list_output(display_cmd).map(|focus| focus.trim().to_string())
+}
+
+/// Selects the window and pane `session_focus` returned.
+/// They may not exist anymore, tmux then keeps the current focus.
+pub(crate) fn restore_focus(name: &str, focus: Option<&str>) -> Result<(), Error> {
+    let Some((window, pane)) = focus.and_then(|focus| focus.split_once('.')) else {
+        return Ok(());
+    };
+
+    Tmux::new()
+        .add_command(TmuxCommand::select_window().target_window(format!("{name}:{window}")))
+        .add_command(TmuxCommand::select_pane().target_pane(format!("{name}:{window}.{pane}")))
+        .output()?;
+    Ok(())
+}
+
+/// The template the session `name` is built from again, the root of a project is updated
+pub(crate) fn session_config(name: &str, tag: SessionTag) -> Result<(SessionTag, Template), Error> {
+    match tag.kind {
+        SessionKind::Project => {
+            let project = find_project(&tag.name)?;
+            let root =
+                absolute_path(&project.root_dir).map_err(|source| SessionError::MissingRoot {
+                    path: project.root_dir.clone(),
+                    source,
+                })?;
+            let tag = SessionTag {
+                root: Some(root),
+                ..tag
+            };
+            Ok((tag, project.into_template()?))
+        }
+        SessionKind::Template => {
+            let template = find_template(&tag.name)?;
+            Ok((tag, template))
+        }
+        SessionKind::Dir => Ok((tag, directory_template(name))),
+    }
+}
+
+/// A directory session has a single window that is named after the session
+pub fn directory_template(name: &str) -> Template {
+    let window = Window {
+        name: Some(name.to_string()),
+        layout: None,
+        dir: None,
+        options: Default::default(),
+        split: None,
+        when: None,
+        panes: Vec::new(),
+    };
+    Template {
+        name: name.to_string(),
+        windows: vec![window],
+        ..Template::default()
+    }
+}
+
+fn session_panes(name: &str) -> Vec<String> {
+    let list_cmd = TmuxCommand::list_panes()
+        .session()
+        .target(name)
+        .format("#{pane_id}");
+    list_output(list_cmd)
+        .map(|panes| panes.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_from_options() {
+        let tag = SessionTag::from_options(Some("project"), Some("work:api"), Some("/src/a:b"));
+        assert_eq!(
+            tag,
+            Some(SessionTag {
+                kind: SessionKind::Project,
+                name: "work:api".to_string(),
+                root: Some(PathBuf::from("/src/a:b")),
+            })
+        );
+        let tag = SessionTag::from_options(Some("dir"), Some("scratch"), Some(""));
+        assert_eq!(tag.unwrap().root, None);
+        assert_eq!(SessionTag::from_options(Some(""), Some(""), Some("")), None);
+        assert_eq!(SessionTag::from_options(None, None, None), None);
+    }
+
+    #[test]
+    fn test_stop_targets() {
+        let tag = |kind, name: &str| SessionTag {
+            kind,
+            name: name.to_string(),
+            root: None,
+        };
+        let running = ["api", "notes", "other"].map(str::to_string);
+        let managed = [
+            ("api".to_string(), tag(SessionKind::Project, "api")),
+            ("notes".to_string(), tag(SessionKind::Template, "notes")),
+        ];
+        let select = |name, kind| select_stop_targets(name, kind, &running, &managed);
+
+        assert_eq!(select(None, None).unwrap(), ["api", "notes"]);
+        assert_eq!(
+            select(None, Some(SessionKind::Template)).unwrap(),
+            ["notes"]
+        );
+        assert_eq!(select(Some("api"), None).unwrap(), ["api"]);
+        assert_eq!(
+            select(Some("api"), Some(SessionKind::Project)).unwrap(),
+            ["api"]
+        );
+        assert!(matches!(
+            select(Some("api"), Some(SessionKind::Dir)),
+            Err(Error::Session(SessionError::WrongKind { .. }))
+        ));
+        assert!(matches!(
+            select(Some("other"), None),
+            Err(Error::Session(SessionError::NotManaged { .. }))
+        ));
+        assert!(matches!(
+            select(Some("gone"), None),
+            Err(Error::Session(SessionError::NotRunning { .. }))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    freeze::session_windows,
+    layout::layout_pane_count,
+    projects::find_project,
+    session::{
+        managed_sessions, restore_focus, session_config, session_focus, SessionError, SessionKind,
+    },
+    templates::{apply_window, created_windows, find_template, pane_waits, sync_window, Window},
+    variables::Variables,
+    Error,
+};
+use std::path::Path;
+use tmux_interface::{KillWindow, Tmux};
+
+/// Syncs every running session of the config `name` of `kind`, returns the synced sessions
+pub fn sync_sessions(
+    kind: SessionKind,
+    name: &str,
+    vars: &Variables,
+    prune: bool,
+) -> Result<Vec<String>, Error> {
+    let name = match kind {
+        SessionKind::Project => find_project(name)?.name,
+        SessionKind::Template => find_template(name)?.name,
+        SessionKind::Dir => name.to_string(),
+    };
+    let sessions: Vec<_> = managed_sessions()?
+        .into_iter()
+        .filter(|(_, tag)| tag.kind == kind && tag.name == name)
+        .collect();
+    if sessions.is_empty() {
+        return Err(SessionError::NoSession { kind, name }.into());
+    }
+
+    for (session, tag) in &sessions {
+        let (tag, template) = session_config(session, tag.clone())?;
+        let windows = template.resolve_windows(vars)?;
+        sync_session(session, &windows, tag.root.as_deref(), prune)?;
+    }
+
+    Ok(sessions.into_iter().map(|(session, _)| session).collect())
+}
+
+/// Brings the running session in line with the windows without killing any pane.
+/// Missing windows and panes are created, windows are renamed and their layouts applied again.
+/// With `prune` the windows that aren't in the config anymore are killed.
+pub fn sync_session(
+    session: &str,
+    windows: &[Window],
+    root: Option<&Path>,
+    prune: bool,
+) -> Result<(), Error> {
+    // Fails before anything is changed if a wait watches a pane that isn't created
+    pane_waits(windows, root)?;
+    let live = session_windows(session)?;
+    let created = created_windows(windows, root);
+    let config_windows: Vec<_> = created
+        .iter()
+        .map(|(window_idx, window, _)| (*window_idx, window.name.as_deref()))
+        .collect();
+    let live_windows: Vec<_> = live
+        .iter()
+        .map(|window| (window.name.as_str(), window.config_index))
+        .collect();
+    let matches = match_windows(&config_windows, &live_windows);
+
+    // Existing panes are kept, so a fixed layout has to fit them before anything is changed
+    for ((_, window, panes), matched) in created.iter().zip(&matches) {
+        let live_panes = matched.map_or(0, |idx| live[idx].panes.len());
+        check_pane_count(window, panes.len().max(live_panes))?;
+    }
+
+    let focus = session_focus(session);
+    for ((window_idx, window, panes), matched) in created.iter().zip(&matches) {
+        let tmux = match matched {
+            Some(idx) => {
+                let target = format!("{session}:{}", live[*idx].index);
+                let live_panes = live[*idx].panes.len();
+                sync_window(
+                    Tmux::new(),
+                    &target,
+                    *window_idx,
+                    window,
+                    panes,
+                    live_panes,
+                    root,
+                )
+            }
+            None => apply_window(
+                Tmux::new(),
+                session,
+                false,
+                *window_idx,
+                window,
+                panes,
+                root,
+            ),
+        };
+
+        // A failing command stops the rest of the window, like a layout that doesn't fit
+        let output = tmux.output()?;
+        if !output.success() {
+            let name = window.name.clone().unwrap_or_default();
+            return Err(SessionError::SyncWindow {
+                window: name,
+                message: String::from_utf8_lossy(&output.stderr()).trim().to_string(),
+            }
+            .into());
+        }
+    }
+
+    if prune {
+        let pruned = live
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !matches.contains(&Some(*idx)))
+            .map(|(_, window)| {
+                KillWindow::new().target_window(format!("{session}:{}", window.index))
+            });
+        pruned
+            .fold(Tmux::new(), |tmux, cmd| tmux.add_command(cmd))
+            .output()?;
+    }
+
+    restore_focus(session, focus.as_deref())
+}
+
+/// A layout with a fixed number of panes only fits if the synced window has that many panes
+fn check_pane_count(window: &Window, panes: usize) -> Result<(), SessionError> {
+    match window.layout.as_deref().and_then(layout_pane_count) {
+        Some(leaves) if leaves != panes => Err(SessionError::SyncPaneCount {
+            window: window.name.clone().unwrap_or_default(),
+            leaves,
+            panes,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The live window every window of the config belongs to, `None` if it has to be created.
+/// Windows with the same name belong together, the others are found by the index of the
+/// config window moxide tagged them with. Windows created by the user are never taken over.
+fn match_windows(
+    config: &[(usize, Option<&str>)],
+    live: &[(&str, Option<usize>)],
+) -> Vec<Option<usize>> {
+    let mut used = vec![false; live.len()];
+    let mut matches: Vec<_> = config
+        .iter()
+        .map(|(_, name)| claim(&mut used, |idx| Some(live[idx].0) == *name))
+        .collect();
+    for ((window_idx, _), matched) in config.iter().zip(matches.iter_mut()) {
+        if matched.is_none() {
+            *matched = claim(&mut used, |idx| live[idx].1 == Some(*window_idx));
+        }
+    }
+    matches
+}
+
+/// The first live window that isn't `used` yet and matches, it is marked as used
+fn claim(used: &mut [bool], is_match: impl Fn(usize) -> bool) -> Option<usize> {
+    let idx = (0..used.len()).find(|idx| !used[*idx] && is_match(*idx))?;
+    used[idx] = true;
+    Some(idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_windows() {
+        let live = [("editor", Some(0)), ("scratch", None), ("server", Some(2))];
+        assert_eq!(
+            match_windows(&[(0, Some("server")), (1, Some("editor"))], &live),
+            [Some(2), Some(0)]
+        );
+        // Renamed windows are found by their tag, windows of the user are left alone
+        assert_eq!(
+            match_windows(
+                &[
+                    (0, Some("editor")),
+                    (1, Some("logs")),
+                    (2, Some("api")),
+                    (3, None)
+                ],
+                &live
+            ),
+            [Some(0), None, Some(2), None]
+        );
+    }
+
+    #[test]
+    fn test_check_pane_count() {
+        let window: Window = serde_yaml::from_str(
+            "name: Server
+layout: 5e0c,200x50,0,0{100x50,0,0,0,99x50,101,0,1}
+panes: [cargo run, htop]",
+        )
+        .unwrap();
+        assert!(check_pane_count(&window, 2).is_ok());
+        assert!(matches!(
+            check_pane_count(&window, 3),
+            Err(SessionError::SyncPaneCount {
+                leaves: 2,
+                panes: 3,
+                ..
+            })
+        ));
+
+        let window = Window {
+            layout: Some("tiled".to_string()),
+            ..window
+        };
+        assert!(check_pane_count(&window, 3).is_ok());
+    }
+}
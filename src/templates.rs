@@ -1,138 +1,1355 @@
 use crate::{
-    helpers::{get_config_dir, Exit},
+    conditions::Condition,
+    config_files::warn,
+    environment::{load_environment, Environment, EnvironmentError},
+    formats::{optional_scalar_map, scalar_map},
+    helpers::{apply_if_some, get_config_dir},
+    index::{Index, IndexEntry, Indexed},
+    layout::{layout_pane_count, Split, SplitError},
+    readiness::{type_when_idle, PaneWait, WaitError, WaitFor},
+    schema::{layout_schema, optional_scalars_schema, scalars_schema},
+    tmux::{self, with_environment},
+    variables::{substitute, VariableError, Variables},
     widgets::table::Table,
+    Error,
 };
-use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
-use tmux_interface::{Tmux, TmuxCommand};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    cell::LazyCell,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tmux_interface::{PaneSize, RespawnPane, SetOption, SetWindowOption, Tmux, TmuxCommand};
+
+/// tmux options with their values, booleans are translated into `on` and `off`
+pub type Options = BTreeMap<String, String>;
+
+/// The window option moxide tags the windows it creates with, it holds the index of the window
+pub(crate) const WINDOW_OPTION: &str = "@moxide-window";
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
 pub struct Template {
     pub name: String,
     pub hidden: Option<bool>,
+    /// The name of a template whose windows are inherited
+    pub extends: Option<String>,
+    /// Names of inherited windows that are dropped
+    #[serde(default)]
+    pub exclude_windows: Vec<String>,
+    /// Parameters of the template, a parameter without a default must be passed on start
+    #[serde(default, deserialize_with = "optional_scalar_map")]
+    #[schemars(schema_with = "optional_scalars_schema")]
+    pub vars: BTreeMap<String, Option<String>>,
+    /// Environment variables of the session
+    #[serde(default, deserialize_with = "scalar_map")]
+    #[schemars(schema_with = "scalars_schema")]
+    pub env: Environment,
+    /// A dotenv file, relative to the root of the session
+    pub env_file: Option<PathBuf>,
+    /// Shell commands that run before the session is created
+    #[serde(default)]
+    pub on_create: Vec<String>,
+    /// Shell commands that run whenever a client attaches to the session
+    #[serde(default)]
+    pub on_attach: Vec<String>,
+    /// Shell commands that run when the session is closed
+    #[serde(default)]
+    pub on_stop: Vec<String>,
+    /// tmux options of the session
+    #[serde(default, deserialize_with = "scalar_map")]
+    #[schemars(schema_with = "scalars_schema")]
+    pub options: Options,
+    #[serde(default)]
     pub windows: Vec<Window>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Error)]
+pub enum WindowError {
+    #[error(transparent)]
+    Variable(#[from] VariableError),
+    #[error(transparent)]
+    Split(#[from] SplitError),
+    #[error(transparent)]
+    Wait(#[from] WaitError),
+}
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Template {name} could not be found")]
+    NotFound { name: String },
+    #[error("No template was given")]
+    NoTemplates,
+    #[error("Templates extend each other in a cycle: {}", chain.join(" -> "))]
+    CyclicInheritance { chain: Vec<String> },
+    #[error("The window {window} is defined by both {first} and {second}")]
+    WindowCollision {
+        window: String,
+        first: String,
+        second: String,
+    },
+}
+
+impl Template {
+    /// The declared parameters that have a default value
+    pub fn default_vars(&self) -> Variables {
+        self.vars
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), value.clone()?)))
+            .collect()
+    }
+
+    /// Expands all placeholders in the windows and compiles their split trees,
+    /// `overrides` take precedence over the defaults
+    pub fn resolve_windows(&self, overrides: &Variables) -> Result<Vec<Window>, WindowError> {
+        let mut vars = self.default_vars();
+        vars.extend(overrides.clone());
+
+        self.windows
+            .iter()
+            .map(|window| {
+                window.check_waits()?;
+                let window = window.substitute(&vars)?.compile_split()?;
+                window.check_conditional_panes()?;
+                Ok(window)
+            })
+            .collect()
+    }
+
+    /// The environment of the session, `env_file` is resolved relative to `root`
+    pub fn environment(&self, root: Option<&Path>) -> Result<Environment, EnvironmentError> {
+        load_environment(&self.env, self.env_file.as_deref(), root)
+    }
+
+    /// Merges the template with the template it extends, `lookup` is used to find the parents
+    pub fn resolve_extends<F, E>(self, lookup: &F) -> Result<Self, E>
+    where
+        F: Fn(&str) -> Result<Option<Self>, E>,
+        E: From<TemplateError>,
+    {
+        let mut chain = vec![self.name.clone()];
+        self.resolve_extends_chain(lookup, &mut chain)
+    }
+
+    fn resolve_extends_chain<F, E>(self, lookup: &F, chain: &mut Vec<String>) -> Result<Self, E>
+    where
+        F: Fn(&str) -> Result<Option<Self>, E>,
+        E: From<TemplateError>,
+    {
+        let Some(base_name) = self.extends.clone() else {
+            return Ok(self);
+        };
+
+        let is_cyclic = chain.contains(&base_name);
+        chain.push(base_name.clone());
+        if is_cyclic {
+            return Err(TemplateError::CyclicInheritance {
+                chain: chain.clone(),
+            }
+            .into());
+        }
+
+        let base = lookup(&base_name)?.ok_or(TemplateError::NotFound { name: base_name })?;
+        let base = base.resolve_extends_chain(lookup, chain)?;
+
+        Ok(self.inherit(base))
+    }
+
+    /// Concatenates the windows of the templates in order,
+    /// settings of later templates take precedence
+    fn compose(first: Self, rest: Vec<Self>) -> Result<Self, TemplateError> {
+        let mut owners: BTreeMap<String, String> = first
+            .windows
+            .iter()
+            .filter_map(|window| Some((window.name.clone()?, first.name.clone())))
+            .collect();
+
+        rest.into_iter().try_fold(first, |mut composed, template| {
+            for name in template.windows.iter().filter_map(|w| w.name.as_ref()) {
+                if let Some(owner) = owners.insert(name.clone(), template.name.clone()) {
+                    return Err(TemplateError::WindowCollision {
+                        window: name.clone(),
+                        first: owner,
+                        second: template.name,
+                    });
+                }
+            }
+
+            composed.windows.extend(template.windows);
+            for (key, value) in template.vars {
+                // A parameter without a default keeps the default of an earlier template
+                let default = composed.vars.entry(key).or_default();
+                if value.is_some() {
+                    *default = value;
+                }
+            }
+            composed.env.extend(template.env);
+            composed.env_file = template.env_file.or(composed.env_file);
+            composed.on_create.extend(template.on_create);
+            composed.on_attach.extend(template.on_attach);
+            composed.on_stop.extend(template.on_stop);
+            composed.options.extend(template.options);
+            Ok(composed)
+        })
+    }
+
+    /// Windows with the same name as an inherited window replace it, all others are appended
+    pub(crate) fn inherit(self, base: Self) -> Self {
+        let mut windows = base.windows;
+        windows.retain(|window| {
+            window
+                .name
+                .as_ref()
+                .is_none_or(|name| !self.exclude_windows.contains(name))
+        });
+
+        for window in self.windows {
+            let replaced = windows
+                .iter_mut()
+                .find(|base_window| window.name.is_some() && base_window.name == window.name);
+            match replaced {
+                Some(base_window) => *base_window = window,
+                None => windows.push(window),
+            }
+        }
+
+        let mut vars = base.vars;
+        vars.extend(self.vars);
+        let mut env = base.env;
+        env.extend(self.env);
+        let [on_create, on_attach, on_stop] = [
+            (base.on_create, self.on_create),
+            (base.on_attach, self.on_attach),
+            (base.on_stop, self.on_stop),
+        ]
+        .map(|(base_hooks, hooks)| [base_hooks, hooks].concat());
+        let mut options = base.options;
+        options.extend(self.options);
+
+        Self {
+            name: self.name,
+            hidden: self.hidden,
+            extends: self.extends,
+            exclude_windows: Vec::new(),
+            vars,
+            env,
+            env_file: self.env_file.or(base.env_file),
+            on_create,
+            on_attach,
+            on_stop,
+            options,
+            windows,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Eq, Clone)]
 pub struct Window {
     pub name: Option<String>,
+    /// A tmux layout preset or a custom layout string
+    #[serde(default)]
+    #[schemars(schema_with = "layout_schema")]
     pub layout: Option<String>,
-    pub panes: Vec<String>,
+    /// The start directory of the window, relative to the root of the session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// tmux options of the window
+    #[serde(
+        default,
+        deserialize_with = "scalar_map",
+        skip_serializing_if = "Options::is_empty"
+    )]
+    #[schemars(schema_with = "scalars_schema")]
+    pub options: Options,
+    /// A tree of splits that is compiled into the layout of the window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<Split>,
+    /// The window is only created if the condition is met
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<Condition>,
+    pub panes: Vec<Pane>,
+}
+
+/// A pane is either written as a plain command or as a mapping with additional options
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Pane {
+    pub command: String,
+    /// The start directory, relative to the directory of the window
+    pub cwd: Option<PathBuf>,
+    pub env: Environment,
+    pub title: Option<String>,
+    /// Select this pane after the window is set up
+    pub focus: bool,
+    /// The size of the split that creates this pane, ignored for the first pane
+    pub size: Option<PaneSize>,
+    /// The pane is only created if the condition is met
+    pub when: Option<Condition>,
+    /// The command is only sent once the conditions pass
+    pub wait_for: Option<WaitFor>,
+    /// Run the command as the process of the pane instead of typing it into a shell
+    pub exec: bool,
+}
+
+impl Pane {
+    fn is_plain(&self) -> bool {
+        self == &Self::from(self.command.clone())
+    }
+
+    fn substitute(&self, vars: &Variables) -> Result<Self, VariableError> {
+        Ok(Self {
+            command: substitute(&self.command, vars)?,
+            cwd: self
+                .cwd
+                .as_ref()
+                .map(|cwd| substitute(&cwd.to_string_lossy(), vars).map(PathBuf::from))
+                .transpose()?,
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), substitute(value, vars)?)))
+                .collect::<Result<_, _>>()?,
+            title: self
+                .title
+                .as_deref()
+                .map(|title| substitute(title, vars))
+                .transpose()?,
+            ..self.clone()
+        })
+    }
+
+    fn start_directory(&self, dir: Option<&Path>) -> Option<PathBuf> {
+        join_dirs(dir, self.cwd.as_deref())
+    }
+}
+
+/// Resolves `relative` against `base`, absolute paths are kept as they are
+fn join_dirs(base: Option<&Path>, relative: Option<&Path>) -> Option<PathBuf> {
+    match (base, relative) {
+        (Some(base), Some(relative)) => Some(base.join(relative)),
+        (None, Some(relative)) => Some(relative.to_path_buf()),
+        (base, None) => base.map(Path::to_path_buf),
+    }
+}
+
+impl From<String> for Pane {
+    fn from(command: String) -> Self {
+        Self {
+            command,
+            ..Self::default()
+        }
+    }
+}
+
+fn parse_pane_size(size: &str) -> Option<PaneSize> {
+    match size.trim().strip_suffix('%') {
+        Some(percentage) => percentage.trim().parse().ok().map(PaneSize::Percentage),
+        None => size.trim().parse().ok().map(PaneSize::Size),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(rename = "PaneSize")]
+enum RawSize {
+    Lines(usize),
+    /// Lines or a percentage like `30%`
+    Text(String),
+}
+
+/// The mapping form of a pane
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "PaneOptions")]
+struct RawPaneOptions {
+    #[serde(default)]
+    command: String,
+    /// The start directory, relative to the directory of the window
+    cwd: Option<PathBuf>,
+    #[serde(default, deserialize_with = "scalar_map")]
+    #[schemars(schema_with = "scalars_schema")]
+    env: Environment,
+    title: Option<String>,
+    /// Select this pane after the window is set up
+    #[serde(default)]
+    focus: bool,
+    /// The size of the split that creates this pane, ignored for the first pane
+    size: Option<RawSize>,
+    /// The pane is only created if the condition is met
+    when: Option<Condition>,
+    /// The command is only sent once the conditions pass
+    wait_for: Option<WaitFor>,
+    /// Run the command as the process of the pane instead of typing it into a shell
+    #[serde(default)]
+    exec: bool,
+}
+
+impl JsonSchema for Pane {
+    fn schema_name() -> Cow<'static, str> {
+        "Pane".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A command or a mapping with pane options",
+            "anyOf": [{ "type": "string" }, generator.subschema_for::<RawPaneOptions>()],
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Pane {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct PaneVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PaneVisitor {
+            type Value = RawPaneOptions;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a command or a mapping with pane options")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, command: &str) -> Result<Self::Value, E> {
+                Ok(RawPaneOptions {
+                    command: command.to_string(),
+                    cwd: None,
+                    env: Environment::new(),
+                    title: None,
+                    focus: false,
+                    size: None,
+                    when: None,
+                    wait_for: None,
+                    exec: false,
+                })
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<Self::Value, A::Error> {
+                RawPaneOptions::deserialize(serde::de::value::MapAccessDeserializer::new(map))
+            }
+        }
+
+        let raw = deserializer.deserialize_any(PaneVisitor)?;
+
+        let size = match raw.size {
+            None => None,
+            Some(RawSize::Lines(lines)) => Some(PaneSize::Size(lines)),
+            Some(RawSize::Text(text)) => Some(parse_pane_size(&text).ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "Invalid pane size {text}, expected lines or a percentage"
+                ))
+            })?),
+        };
+
+        Ok(Self {
+            command: raw.command,
+            cwd: raw.cwd,
+            env: raw.env,
+            title: raw.title,
+            focus: raw.focus,
+            size,
+            when: raw.when,
+            wait_for: raw.wait_for,
+            exec: raw.exec,
+        })
+    }
+}
+
+impl Serialize for Pane {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.is_plain() {
+            return serializer.serialize_str(&self.command);
+        }
+
+        let mut map = serializer.serialize_map(None)?;
+        if !self.command.is_empty() {
+            map.serialize_entry("command", &self.command)?;
+        }
+        if let Some(cwd) = &self.cwd {
+            map.serialize_entry("cwd", cwd)?;
+        }
+        if !self.env.is_empty() {
+            map.serialize_entry("env", &self.env)?;
+        }
+        if let Some(title) = &self.title {
+            map.serialize_entry("title", title)?;
+        }
+        if self.focus {
+            map.serialize_entry("focus", &self.focus)?;
+        }
+        match &self.size {
+            Some(PaneSize::Size(lines)) => map.serialize_entry("size", lines)?,
+            Some(PaneSize::Percentage(percentage)) => {
+                map.serialize_entry("size", &format!("{percentage}%"))?;
+            }
+            None => {}
+        }
+        if let Some(when) = &self.when {
+            map.serialize_entry("when", when)?;
+        }
+        if let Some(wait_for) = &self.wait_for {
+            map.serialize_entry("wait_for", wait_for)?;
+        }
+        if self.exec {
+            map.serialize_entry("exec", &self.exec)?;
+        }
+        map.end()
+    }
+}
+
+impl Window {
+    /// Returns a copy of the window with every placeholder replaced
+    pub fn substitute(&self, vars: &Variables) -> Result<Self, VariableError> {
+        let substitute_opt = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| substitute(value, vars))
+                .transpose()
+        };
+
+        Ok(Self {
+            name: substitute_opt(&self.name)?,
+            layout: substitute_opt(&self.layout)?,
+            dir: self
+                .dir
+                .as_ref()
+                .map(|dir| substitute(&dir.to_string_lossy(), vars).map(PathBuf::from))
+                .transpose()?,
+            options: self
+                .options
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), substitute(value, vars)?)))
+                .collect::<Result<_, _>>()?,
+            split: self.split.clone(),
+            when: self.when.clone(),
+            panes: self
+                .panes
+                .iter()
+                .map(|pane| pane.substitute(vars))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Sets the layout to the compiled split tree, after checking it against the panes
+    fn compile_split(mut self) -> Result<Self, SplitError> {
+        let Some(split) = &self.split else {
+            return Ok(self);
+        };
+
+        let window = self.name.clone().unwrap_or_default();
+        if self.layout.is_some() {
+            return Err(SplitError::LayoutAndSplit { window });
+        }
+        let leaves = split.pane_count();
+        if leaves != self.panes.len() {
+            return Err(SplitError::PaneCount {
+                window,
+                leaves,
+                panes: self.panes.len(),
+            });
+        }
+
+        self.layout = Some(split.to_layout()?);
+        Ok(self)
+    }
+
+    /// Layout strings, including compiled split trees, only fit if every pane is created
+    fn check_conditional_panes(&self) -> Result<(), SplitError> {
+        let is_fixed = self.layout.as_deref().and_then(layout_pane_count).is_some();
+        if is_fixed && self.panes.iter().any(|pane| pane.when.is_some()) {
+            return Err(SplitError::ConditionalPane {
+                window: self.name.clone().unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Finds mistakes that would make starting the window fail
+    pub fn check(&self) -> Result<(), WindowError> {
+        self.check_waits()?;
+        self.clone().compile_split()?.check_conditional_panes()?;
+
+        let leaves = self.layout.as_deref().and_then(layout_pane_count);
+        match leaves {
+            Some(leaves) if leaves != self.panes.len() => Err(SplitError::LayoutPaneCount {
+                window: self.name.clone().unwrap_or_default(),
+                leaves,
+                panes: self.panes.len(),
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_waits(&self) -> Result<(), WaitError> {
+        let window = self.name.clone().unwrap_or_default();
+        self.panes.iter().enumerate().try_for_each(|(idx, pane)| {
+            pane.wait_for
+                .as_ref()
+                .map_or(Ok(()), |wait| wait.validate(&window, idx))
+        })
+    }
+
+    fn start_directory(&self, dir: Option<&Path>) -> Option<PathBuf> {
+        join_dirs(dir, self.dir.as_deref())
+    }
 }
 
 impl From<&Window> for Table<String, String> {
     fn from(value: &Window) -> Self {
         let name = value.name.clone().unwrap_or_else(|| "No name".to_string());
+        let conditional_panes = value
+            .panes
+            .iter()
+            .filter(|pane| pane.when.is_some())
+            .count();
+
+        let mut panes = format!("{} Panes", value.panes.len());
+        if conditional_panes > 0 {
+            panes.push_str(&format!(" ({conditional_panes} conditional)"));
+        }
+        if value.when.is_some() {
+            panes.push_str(", conditional window");
+        }
 
-        Self::from((name, format!("{} Panes", value.panes.len())))
+        Self::from((name, panes))
     }
 }
 
-pub fn find_template(name: &str) -> Option<Template> {
-    let templates_dir = get_config_dir().join("templates/");
-    let file_path = templates_dir.join(format!("{name}.yaml"));
-    let is_valid_path = file_path.exists() && file_path.is_file();
-    let matching_path = is_valid_path.then_some(file_path);
+impl Indexed for Template {
+    fn name(&self) -> &str {
+        &self.name
+    }
 
-    let matching_template = matching_path.and_then(|path| {
-        let content = fs::read_to_string(&path).ok()?;
-        let template = serde_yaml::from_str::<Template>(&content).ok()?;
-        (template.name == name).then_some(template)
-    });
+    fn hidden(&self) -> bool {
+        self.hidden.unwrap_or(false)
+    }
 
-    if matching_template.is_some() {
-        return matching_template;
+    fn extends(&self) -> Option<&str> {
+        self.extends.as_deref()
     }
+}
 
-    fs::read_dir(&templates_dir)
-        .exit(1, "Can't read template config")
-        .find_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if !path.is_file() {
-                return None;
-            }
+fn template_index() -> Result<Index, Error> {
+    Index::of::<Template>(&get_config_dir()?.join("templates/"))
+}
 
-            let content = fs::read_to_string(&path).ok()?;
-            let template = serde_yaml::from_str::<Template>(&content).ok()?;
-            (template.name == name).then_some(template)
-        })
+fn load_template(index: &Index, name: &str) -> Result<Option<Template>, Error> {
+    let loaded = index.load::<Template>(name)?;
+    Ok(loaded.map(|(name, template)| Template { name, ..template }))
 }
 
-pub fn parse_template_config() -> Vec<Template> {
-    let templates_content =
-        fs::read_dir(get_config_dir().join("templates/")).exit(1, "Can't read template config");
+pub fn find_template(name: &str) -> Result<Template, Error> {
+    let index = template_index()?;
+    let Some(template) = load_template(&index, name)? else {
+        // The template might be in a file that can't be parsed
+        index.warn_broken();
+        return Err(TemplateError::NotFound {
+            name: name.to_string(),
+        }
+        .into());
+    };
 
-    templates_content
+    template.resolve_extends(&|name| load_template(&index, name))
+}
+
+/// Finds all templates and concatenates their windows in order
+pub fn compose_templates(names: &[String]) -> Result<Template, Error> {
+    let mut templates = names
+        .iter()
+        .map(|name| find_template(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    if templates.is_empty() {
+        return Err(TemplateError::NoTemplates.into());
+    }
+
+    let first = templates.remove(0);
+    Ok(Template::compose(first, templates)?)
+}
+
+pub fn parse_template_config() -> Result<Vec<Template>, Error> {
+    let index = template_index()?;
+    index.warn_broken();
+    let templates: Vec<_> = index
+        .configs()
         .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if !path.is_file() {
-                return None;
-            }
+            let mut template = entry.load::<Template>().map_err(warn).ok()?;
+            template.name = entry.name.clone()?;
+            Some(template)
+        })
+        .collect();
 
-            let content = fs::read_to_string(path).ok()?;
-            serde_yaml::from_str::<Template>(&content).ok()
+    let lookup = |name: &str| -> Result<_, Error> {
+        let Some(entry) = index.find(name)? else {
+            return Ok(None);
+        };
+        Ok(templates
+            .iter()
+            .find(|template| Some(&template.name) == entry.name.as_ref())
+            .cloned())
+    };
+    Ok(templates
+        .iter()
+        .filter_map(|template| {
+            template
+                .clone()
+                .resolve_extends(&lookup)
+                .map_err(|err| warn(format!("Skipping template {}: {err}", template.name)))
+                .ok()
         })
-        .collect()
+        .collect())
+}
+
+/// The templates that can be started, read from the index without parsing every file
+pub fn indexed_templates() -> Result<Vec<IndexEntry>, Error> {
+    let index = template_index()?;
+    index.warn_broken();
+
+    Ok(index
+        .configs()
+        .filter(|entry| {
+            check_extends(&index, entry)
+                .map_err(|err| {
+                    let name = entry.name.as_deref().unwrap_or_default();
+                    warn(format!("Skipping template {name}: {err}"));
+                })
+                .is_ok()
+        })
+        .cloned()
+        .collect())
+}
+
+/// Checks that all templates the entry extends exist, like `resolve_extends` does
+fn check_extends(index: &Index, entry: &IndexEntry) -> Result<(), Error> {
+    let mut chain: Vec<_> = entry.name.iter().cloned().collect();
+    let mut current = entry;
+
+    while let Some(base_name) = &current.extends {
+        let is_cyclic = chain.contains(base_name);
+        chain.push(base_name.clone());
+        if is_cyclic {
+            return Err(TemplateError::CyclicInheritance { chain }.into());
+        }
+
+        current = index
+            .find(base_name)?
+            .ok_or_else(|| TemplateError::NotFound {
+                name: base_name.clone(),
+            })?;
+    }
+
+    Ok(())
 }
 
+/// Adds the windows and options to `session`. Every command targets the session explicitly,
+/// otherwise tmux would apply them to the session moxide runs in.
 pub fn apply_windows<'a>(
     tmux: Tmux<'a>,
+    session: &str,
     windows: &'a [Window],
-    dir: Option<&'a PathBuf>,
+    options: &'a Options,
+    dir: Option<&'a Path>,
 ) -> Tmux<'a> {
-    let enumerated = windows.iter().enumerate();
-    enumerated.fold(tmux, |tmux, (window_idx, window)| {
-        let cmd = build_tmux_command(window_idx, window, dir);
-        let tmux = add_panes_to_tmux(tmux.add_command(cmd), &window.panes, dir);
-
-        match window.layout.as_ref() {
-            Some(layout) => {
-                let layout_cmd = TmuxCommand::select_layout().layout_name(layout);
-                tmux.add_command(layout_cmd)
-            }
-            None => tmux,
-        }
+    // The active window and pane of the session, they follow newly created ones
+    let target = format!("{session}:");
+    let tmux = options.iter().fold(tmux, |tmux, (option, value)| {
+        let cmd = SetOption::new()
+            .target_pane(target.clone())
+            .option(option)
+            .value(option_value(value));
+        tmux.add_command(cmd)
+    });
+
+    let enumerated = created_windows(windows, dir).into_iter().enumerate();
+    enumerated.fold(tmux, |tmux, (position, (window_idx, window, panes))| {
+        apply_window(
+            tmux,
+            session,
+            position == 0,
+            window_idx,
+            window,
+            &panes,
+            dir,
+        )
     })
 }
 
-fn add_panes_to_tmux<'a>(tmux: Tmux<'a>, panes: &[String], dir: Option<&'a PathBuf>) -> Tmux<'a> {
-    let enumerated = panes.iter().enumerate();
+/// Adds a window with its panes to `session`, the first window already exists with the session.
+/// The window is tagged with `window_idx`, its index in the definition of the session.
+pub(crate) fn apply_window<'a>(
+    tmux: Tmux<'a>,
+    session: &str,
+    is_first: bool,
+    window_idx: usize,
+    window: &'a Window,
+    panes: &[(usize, &'a Pane)],
+    dir: Option<&Path>,
+) -> Tmux<'a> {
+    let target = format!("{session}:");
+    let window_dir = window.start_directory(dir);
+    // The first window is created together with the session in its root directory
+    let created_in = if is_first {
+        dir.map(Path::to_path_buf)
+    } else {
+        window_dir.clone()
+    };
 
-    enumerated.fold(tmux, |tmux, (pane_idx, command)| {
-        let cmd: TmuxCommand = match (pane_idx, dir) {
-            (0, _) => TmuxCommand::new(),
-            (_, Some(d)) => TmuxCommand::split_window()
-                .start_directory(d.to_string_lossy())
-                .into(),
-            (_, None) => TmuxCommand::split_window().into(),
-        };
+    let cmd = build_tmux_command(&target, is_first, window, window_dir.as_deref());
+    let tmux = tmux.add_command(cmd);
+    let tmux = tag_window(tmux, &target, window_idx);
+    let tmux = set_window_options(tmux, &target, window);
+    let tmux = add_panes_to_tmux(
+        tmux,
+        &target,
+        window,
+        panes,
+        0,
+        window_dir.as_deref(),
+        created_in.as_deref(),
+    );
+    select_layout(tmux, &target, window)
+}
+
+/// Brings the existing window `target` in line with its definition.
+/// It is renamed, tagged and gets the panes it is missing, the `live_panes` it has are kept.
+pub(crate) fn sync_window<'a>(
+    tmux: Tmux<'a>,
+    target: &str,
+    window_idx: usize,
+    window: &'a Window,
+    panes: &[(usize, &'a Pane)],
+    live_panes: usize,
+    dir: Option<&Path>,
+) -> Tmux<'a> {
+    let window_dir = window.start_directory(dir);
+
+    let tmux = apply_if_some(tmux, window.name.as_ref(), |tmux, name| {
+        let rename = TmuxCommand::rename_window().target_window(target.to_string());
+        tmux.add_command(rename.new_name(name))
+    });
+    let tmux = tag_window(tmux, target, window_idx);
+    let tmux = set_window_options(tmux, target, window);
+    let tmux = add_panes_to_tmux(
+        tmux,
+        target,
+        window,
+        panes,
+        live_panes,
+        window_dir.as_deref(),
+        window_dir.as_deref(),
+    );
+    select_layout(tmux, target, window)
+}
+
+/// Stores the index of the window in its definition, so a sync finds the window after it was renamed
+fn tag_window<'a>(tmux: Tmux<'a>, target: &str, window_idx: usize) -> Tmux<'a> {
+    let cmd = SetWindowOption::new()
+        .target_window(target.to_string())
+        .option(WINDOW_OPTION)
+        .value(window_idx.to_string());
+    tmux.add_command(cmd)
+}
 
+fn set_window_options<'a>(tmux: Tmux<'a>, target: &str, window: &'a Window) -> Tmux<'a> {
+    window.options.iter().fold(tmux, |tmux, (option, value)| {
+        let cmd = SetWindowOption::new()
+            .target_window(target.to_string())
+            .option(option)
+            .value(option_value(value));
         tmux.add_command(cmd)
-            .add_command(TmuxCommand::send_keys().key(format!("{command}\r")))
     })
 }
 
+fn select_layout<'a>(tmux: Tmux<'a>, target: &str, window: &'a Window) -> Tmux<'a> {
+    match window.layout.as_ref() {
+        Some(layout) => {
+            let layout_cmd = TmuxCommand::select_layout()
+                .target_pane(target.to_string())
+                .layout_name(layout);
+            tmux.add_command(layout_cmd)
+        }
+        None => tmux,
+    }
+}
+
+/// A window that is created with its panes, both paired with their index in the definition
+pub(crate) type CreatedWindow<'a> = (usize, &'a Window, Vec<(usize, &'a Pane)>);
+
+/// The windows and panes whose conditions are met
+pub(crate) fn created_windows<'a>(
+    windows: &'a [Window],
+    root: Option<&Path>,
+) -> Vec<CreatedWindow<'a>> {
+    let is_met = |when: &Option<Condition>| when.as_ref().is_none_or(|when| when.is_met(root));
+
+    windows
+        .iter()
+        .enumerate()
+        .filter(|(_, window)| is_met(&window.when))
+        .map(|(window_idx, window)| {
+            let panes = window
+                .panes
+                .iter()
+                .enumerate()
+                .filter(|(_, pane)| is_met(&pane.when))
+                .collect();
+            (window_idx, window, panes)
+        })
+        .collect()
+}
+
+/// The position among the created panes of the pane defined at `idx`
+fn created_position(panes: &[(usize, &Pane)], idx: usize) -> Option<usize> {
+    panes.iter().position(|(pane_idx, _)| *pane_idx == idx)
+}
+
+/// All panes of a session started with `apply_windows` that wait for something.
+/// Fails if a pane waits for the output of a pane whose condition isn't met.
+pub fn pane_waits<'a>(
+    windows: &'a [Window],
+    dir: Option<&Path>,
+) -> Result<Vec<PaneWait<'a>>, WaitError> {
+    let mut offset = 0;
+    let mut waits = Vec::new();
+
+    for (_, window, panes) in created_windows(windows, dir) {
+        let window_dir = window.start_directory(dir);
+        for (pane_idx, pane) in &panes {
+            let Some(wait) = &pane.wait_for else {
+                continue;
+            };
+            let output_pane = match wait.output_pane(*pane_idx) {
+                Some(watched) => {
+                    let position = created_position(&panes, watched).ok_or_else(|| {
+                        WaitError::SkippedPane {
+                            window: window.name.clone().unwrap_or_default(),
+                            pane: *pane_idx,
+                            watched,
+                        }
+                    })?;
+                    Some(offset + position)
+                }
+                None => None,
+            };
+            waits.push(PaneWait {
+                wait,
+                dir: pane.start_directory(window_dir.as_deref()),
+                output_pane,
+            });
+        }
+        offset += panes.len();
+    }
+
+    Ok(waits)
+}
+
+/// The command line a pane runs, prefixed with its readiness wait
+fn pane_command_line(pane: &Pane, panes: &[(usize, &Pane)], pane_idx: usize) -> Option<String> {
+    let Some(wait) = &pane.wait_for else {
+        return (!pane.command.is_empty()).then(|| pane.command.clone());
+    };
+
+    let output_pane = wait
+        .output_pane(pane_idx)
+        .and_then(|idx| created_position(panes, idx));
+    let wait_cmd = wait.shell_command(output_pane);
+    if pane.command.is_empty() {
+        Some(wait_cmd)
+    } else {
+        Some(format!("{wait_cmd} && {}", pane.command))
+    }
+}
+
+/// Creates the panes of a window, the first `existing` panes are already there and kept as is
+fn add_panes_to_tmux<'a>(
+    tmux: Tmux<'a>,
+    target: &str,
+    window: &Window,
+    panes: &[(usize, &'a Pane)],
+    existing: usize,
+    dir: Option<&Path>,
+    created_in: Option<&Path>,
+) -> Tmux<'a> {
+    // Panes are numbered by position, new panes are split from the last one to keep the order
+    let base_index = LazyCell::new(|| pane_base_index(window));
+    let enumerated = panes.iter().enumerate().skip(existing);
+
+    let tmux = enumerated.fold(tmux, |tmux, (position, (pane_idx, pane))| {
+        let start_dir = pane.start_directory(dir);
+        let command_line = pane_command_line(pane, panes, *pane_idx);
+        let mut cmd = if position == 0 {
+            // The first pane already exists, it only has to be restarted if it needs another
+            // directory, environment or process than the one it was created with
+            if start_dir.as_deref() != created_in || !pane.env.is_empty() || pane.exec {
+                let respawn = RespawnPane::new().kill().target_pane(target.to_string());
+                let respawn = apply_if_some(respawn, start_dir.as_deref(), |cmd, d| {
+                    cmd.start_directory(path_to_string(d))
+                });
+                with_environment(respawn.into(), &pane.env)
+            } else {
+                TmuxCommand::new()
+            }
+        } else {
+            let last_pane = format!("{target}.{}", *base_index + position - 1);
+            let split = TmuxCommand::split_window().target_pane(last_pane);
+            let split = apply_if_some(split, start_dir.as_deref(), |cmd, d| {
+                cmd.start_directory(path_to_string(d))
+            });
+            let split = apply_if_some(split, pane.size.as_ref(), |cmd, size| cmd.size(size));
+            with_environment(split.into(), &pane.env)
+        };
+
+        let tmux = match command_line {
+            Some(command_line) if pane.exec => {
+                cmd.push_param(command_line);
+                tmux.add_command(cmd)
+            }
+            Some(command_line) => tmux
+                .add_command(cmd)
+                .add_command(type_when_idle(target, &command_line)),
+            None => tmux.add_command(cmd),
+        };
+        apply_if_some(tmux, pane.title.as_ref(), |tmux, title| {
+            let select_cmd = TmuxCommand::select_pane().target_pane(target.to_string());
+            tmux.add_command(select_cmd.title(title))
+        })
+    });
+
+    let focused = panes
+        .iter()
+        .skip(existing)
+        .rposition(|(_, pane)| pane.focus);
+    match focused {
+        Some(position) => {
+            let idx = *base_index + existing + position;
+            tmux.add_command(TmuxCommand::select_pane().target_pane(format!("{target}.{idx}")))
+        }
+        None => tmux,
+    }
+}
+
+/// The index of the first pane, a `pane-base-index` option of the window wins over the global one
+fn pane_base_index(window: &Window) -> usize {
+    window
+        .options
+        .get("pane-base-index")
+        .and_then(|idx| idx.parse().ok())
+        .unwrap_or_else(tmux::pane_base_index)
+}
+
+fn option_value(value: &str) -> &str {
+    match value {
+        "true" => "on",
+        "false" => "off",
+        value => value,
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
 fn build_tmux_command<'a>(
-    window_idx: usize,
+    target: &str,
+    is_first: bool,
     window: &'a Window,
-    dir: Option<&'a PathBuf>,
+    dir: Option<&Path>,
 ) -> TmuxCommand<'a> {
-    if window_idx == 0 {
+    if is_first {
         window
             .name
             .as_ref()
-            .map(|name| TmuxCommand::rename_window().new_name(name).into())
+            .map(|name| {
+                let rename = TmuxCommand::rename_window().target_window(target.to_string());
+                rename.new_name(name).into()
+            })
             .unwrap_or_default()
     } else {
-        let name = window.name.as_ref();
-        let new_win = name.map_or_else(TmuxCommand::new_window, |name| {
-            TmuxCommand::new_window().window_name(name)
+        let new_win = TmuxCommand::new_window().target_window(target.to_string());
+        let new_win = apply_if_some(new_win, window.name.as_ref(), |cmd, name| {
+            cmd.window_name(name)
         });
-        match dir {
-            Some(d) => new_win.start_directory(d.to_string_lossy()).into(),
-            None => new_win.into(),
+        apply_if_some(new_win, dir, |cmd, d| {
+            cmd.start_directory(path_to_string(d))
+        })
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extends() {
+        let templates: Vec<Template> = [
+            "name: Base
+windows:
+  - name: Editor
+    panes: [nvim]
+  - name: Git
+    panes: [lazygit]
+  - name: Notes
+    panes: [nvim notes.md]",
+            "name: Rust
+extends: Base
+exclude_windows: [Notes]
+windows:
+  - name: Git
+    panes: [gitui]
+  - name: Cargo
+    panes: [cargo watch]",
+            "name: A
+extends: B
+windows: []",
+            "name: B
+extends: A
+windows: []",
+        ]
+        .iter()
+        .map(|yaml| serde_yaml::from_str(yaml).unwrap())
+        .collect();
+        let lookup = |name: &str| Ok(templates.iter().find(|t| t.name == name).cloned());
+
+        let rust = lookup("Rust").unwrap().unwrap();
+        let rust = rust.resolve_extends(&lookup).unwrap();
+        let names: Vec<_> = rust
+            .windows
+            .iter()
+            .filter_map(|w| w.name.as_deref())
+            .collect();
+        assert_eq!(names, ["Editor", "Git", "Cargo"]);
+        assert_eq!(rust.windows[1].panes, [Pane::from("gitui".to_string())]);
+
+        let a = lookup("A").unwrap().unwrap();
+        let err: TemplateError = a.resolve_extends(&lookup).unwrap_err();
+        assert!(matches!(
+            err,
+            TemplateError::CyclicInheritance { chain } if chain == ["A", "B", "A"]
+        ));
+    }
+
+    #[test]
+    fn test_compose() {
+        let [rust, docker, notes]: [Template; 3] = [
+            "name: Rust
+vars:
+  port: 8080
+env:
+  RUST_LOG: info
+windows:
+  - name: Cargo
+    panes: [cargo watch]",
+            "name: Docker
+vars:
+  port: ~
+env:
+  RUST_LOG: debug
+windows:
+  - name: Compose
+    panes: [docker compose up]",
+            "name: Notes
+windows:
+  - name: Cargo
+    panes: [nvim notes.md]",
+        ]
+        .map(|yaml| serde_yaml::from_str(yaml).unwrap());
+
+        let composed = Template::compose(rust.clone(), vec![docker]).unwrap();
+        let names: Vec<_> = composed
+            .windows
+            .iter()
+            .filter_map(|w| w.name.as_deref())
+            .collect();
+        assert_eq!(names, ["Cargo", "Compose"]);
+        assert_eq!(composed.env["RUST_LOG"], "debug");
+        assert_eq!(composed.vars["port"].as_deref(), Some("8080"));
+
+        let err = Template::compose(rust, vec![notes]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The window Cargo is defined by both Rust and Notes"
+        );
+    }
+
+    #[test]
+    fn test_conditional_panes() {
+        let template: Template = serde_yaml::from_str(
+            "name: Web
+windows:
+  - name: Dev
+    layout: even-horizontal
+    panes:
+      - yarn dev
+      - command: yarn storybook
+        when:
+          exists: .storybook/
+  - name: Logs
+    split:
+      direction: vertical
+      children: [pane, pane]
+    panes:
+      - tail -f dev.log
+      - command: tail -f ci.log
+        when:
+          env: CI",
+        )
+        .unwrap();
+
+        assert!(template.windows[0].check().is_ok());
+        let err = template.resolve_windows(&Variables::new()).unwrap_err();
+        assert!(matches!(
+            err,
+            WindowError::Split(SplitError::ConditionalPane { window }) if window == "Logs"
+        ));
+    }
+
+    #[test]
+    fn test_wait_for_skipped_pane() {
+        let windows: Vec<Window> = serde_yaml::from_str(
+            "- name: Dev
+  panes:
+    - command: yarn dev
+      when:
+        env: MOXIDE_TEST_UNSET_VARIABLE
+    - command: yarn test
+      wait_for:
+        output: ready",
+        )
+        .unwrap();
+
+        let err = pane_waits(&windows, None).unwrap_err();
+        assert!(matches!(
+            err,
+            WaitError::SkippedPane {
+                pane: 1,
+                watched: 0,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_pane_forms() {
+        let panes: Vec<Pane> = serde_yaml::from_str(
+            "- nvim
+- command: yarn dev
+  cwd: web
+  env:
+    PORT: 3000
+  focus: true
+  size: 30%",
+        )
+        .unwrap();
+
+        assert_eq!(panes[0], Pane::from("nvim".to_string()));
+        assert_eq!(
+            panes[1],
+            Pane {
+                command: "yarn dev".to_string(),
+                cwd: Some(PathBuf::from("web")),
+                env: Environment::from([("PORT".to_string(), "3000".to_string())]),
+                title: None,
+                focus: true,
+                size: Some(PaneSize::Percentage(30)),
+                when: None,
+                wait_for: None,
+                exec: false,
+            }
+        );
+        assert_eq!(serde_yaml::to_string(&panes[0]).unwrap(), "nvim\n");
+        assert!(serde_yaml::from_str::<Pane>("size: huge").is_err());
+    }
+
+    /// The arguments of every command in the chain
+    fn chained_commands(tmux: Tmux) -> Vec<Vec<String>> {
+        let command = tmux.into_command();
+        let args: Vec<_> = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        args.split(|arg| arg == ";")
+            .filter(|args| !args.is_empty())
+            .map(<[String]>::to_vec)
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_windows_targets() {
+        let windows: Vec<Window> = serde_yaml::from_str(
+            "- name: Editor
+  panes: [nvim]
+- name: Server
+  dir: server
+  options:
+    pane-base-index: 1
+  panes:
+    - cargo watch
+    - command: cargo run
+      cwd: api
+      title: api
+      focus: true
+    - htop",
+        )
+        .unwrap();
+
+        let options = Options::from([("status".to_string(), "false".to_string())]);
+        let tmux = apply_windows(Tmux::new(), "dev", &windows, &options, None);
+        let commands = chained_commands(tmux);
+        for args in &commands {
+            let target = args.iter().position(|arg| arg == "-t");
+            assert!(
+                target.is_some_and(|idx| args[idx + 1].starts_with("dev:")),
+                "{args:?} doesn't target the session"
+            );
         }
+        assert_eq!(commands[0], ["set", "-t", "dev:", "status", "off"]);
+        assert!(commands.contains(&vec![
+            "setw".to_string(),
+            "-t".to_string(),
+            "dev:".to_string(),
+            "pane-base-index".to_string(),
+            "1".to_string(),
+        ]));
+        assert!(commands.contains(&vec![
+            "setw".to_string(),
+            "-t".to_string(),
+            "dev:".to_string(),
+            WINDOW_OPTION.to_string(),
+            "1".to_string(),
+        ]));
+        assert_eq!(commands.last().unwrap(), &["selectp", "-t", "dev:.2"]);
+    }
+
+    #[test]
+    fn test_sync_window_appends_panes() {
+        let window: Window = serde_yaml::from_str(
+            "name: Server
+options:
+  pane-base-index: 1
+panes:
+  - cargo watch
+  - htop
+  - command: cargo run
+    focus: true",
+        )
+        .unwrap();
+        let panes: Vec<_> = window.panes.iter().enumerate().collect();
+
+        let tmux = sync_window(Tmux::new(), "dev:3", 0, &window, &panes, 2, None);
+        let commands = chained_commands(tmux);
+        // The new pane is split from the last live pane, not from the active one
+        let splits: Vec<_> = commands.iter().filter(|args| args[0] == "splitw").collect();
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0][1..3], ["-t", "dev:3.2"]);
+        assert_eq!(commands.last().unwrap(), &["selectp", "-t", "dev:3.3"]);
     }
 }
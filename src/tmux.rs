@@ -1,6 +1,6 @@
-use crate::helpers::runs_in_tmux;
+use crate::{environment::Environment, helpers::runs_in_tmux};
 use std::borrow::Cow;
-use tmux_interface::{Error, HasSession, Tmux, TmuxCommand};
+use tmux_interface::{Error, HasSession, ShowOptions, Tmux, TmuxCommand};
 
 pub fn attach<'a, S: Into<Cow<'a, str>>>(name: S) -> TmuxCommand<'a> {
     if runs_in_tmux() {
@@ -28,3 +28,26 @@ pub fn get_unused_name(name: &str) -> String {
 
     new_name
 }
+
+/// The global `pane-base-index` option, tmux numbers panes from 0 unless it is set
+pub fn pane_base_index() -> usize {
+    let show = ShowOptions::new()
+        .global()
+        .window()
+        .value()
+        .option("pane-base-index");
+    Tmux::with_command(show)
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout()).ok())
+        .and_then(|idx| idx.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Adds a `-e` flag for every variable, the command must not have positional arguments yet
+pub fn with_environment<'a>(mut cmd: TmuxCommand<'a>, env: &Environment) -> TmuxCommand<'a> {
+    for (key, value) in env {
+        cmd.push_option("-e", format!("{key}={value}"));
+    }
+    cmd
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+pub type Variables = BTreeMap<String, String>;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VariableError {
+    #[error("The variable {name} has no value, pass it with --var {name}=<value>")]
+    Unresolved { name: String },
+    #[error("The placeholder in \"{input}\" is never closed")]
+    Unclosed { input: String },
+}
+
+/// Replaces every `{{name}}` placeholder in `input` with its value from `vars`.
+/// Whitespace around the name is ignored, so `{{ name }}` works as well.
+/// Braces around anything but a name, like `{{.Names}}` of docker, are kept as they are
+/// and `\{{` is written as a literal `{{`.
+pub fn substitute(input: &str, vars: &Variables) -> Result<String, VariableError> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("{{") {
+        let after_open = &rest[start + 2..];
+        if let Some(before) = rest[..start].strip_suffix('\\') {
+            output.push_str(before);
+            output.push_str("{{");
+            rest = after_open;
+            continue;
+        }
+        output.push_str(&rest[..start]);
+
+        let end = after_open
+            .find("}}")
+            .ok_or_else(|| VariableError::Unclosed {
+                input: input.to_string(),
+            })?;
+        let name = after_open[..end].trim();
+        if is_variable_name(name) {
+            let value = vars.get(name).ok_or_else(|| VariableError::Unresolved {
+                name: name.to_string(),
+            })?;
+            output.push_str(value);
+        } else {
+            output.push_str(&rest[start..start + end + 4]);
+        }
+
+        rest = &after_open[end + 2..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Names start with a letter or `_` and continue with letters, digits, `_` or `-`
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parses a `key=value` pair as it is passed to `--var`
+pub fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("Expected key=value, got {arg}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute() {
+        let vars = Variables::from([
+            ("port".to_string(), "3000".to_string()),
+            ("package".to_string(), "web".to_string()),
+        ]);
+
+        assert_eq!(
+            substitute("yarn workspace {{package}} dev --port {{ port }}", &vars),
+            Ok("yarn workspace web dev --port 3000".to_string())
+        );
+        assert_eq!(substitute("nvim", &vars), Ok("nvim".to_string()));
+        assert_eq!(
+            substitute("echo {{missing}}", &vars),
+            Err(VariableError::Unresolved {
+                name: "missing".to_string()
+            })
+        );
+        assert!(matches!(
+            substitute("echo {{port", &vars),
+            Err(VariableError::Unclosed { .. })
+        ));
+        assert_eq!(
+            substitute("docker ps --format '{{.Names}}' -p {{port}}", &vars),
+            Ok("docker ps --format '{{.Names}}' -p 3000".to_string())
+        );
+        assert_eq!(
+            substitute(r"echo \{{port}} {{ }}", &vars),
+            Ok("echo {{port}} {{ }}".to_string())
+        );
+    }
+}